@@ -0,0 +1,152 @@
+use base64::decode;
+use glob::Pattern;
+use network;
+use reqwest;
+use serde_json;
+use std::fs;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use types;
+
+/**
+ * Downloads files matching the --collect patterns out of the task's sandbox once it has stopped.
+ **/
+
+const READ_SIZE: u64 = 1000000; // Download in 1MB chunks
+const MAX_DEPTH: u32 = 16;
+const DIRECTORY_MODE: u32 = 0o040000;
+const FILE_TYPE_MASK: u32 = 0o170000;
+
+fn list_files(agent_url: &str, client: &reqwest::Client, path: &str) -> Result<Vec<types::FileInfo>, String> {
+
+    let request = types::ListFilesRequest {
+        request_type: String::from("LIST_FILES"),
+        list_files: types::ListFilesRequestData {
+            path: String::from(path)
+        }
+    };
+
+    let body_content = serde_json::to_string(&request).unwrap();
+
+    match network::post(client, agent_url, body_content).and_then(|mut response| network::read_json::<types::ListFilesResponse>(&mut response)) {
+        Ok(response) => Ok(response.list_files.file_infos),
+        Err(e) => Err(format!("Unable to list {}: {}", path, e))
+    }
+
+}
+
+/**
+ * Walks the sandbox and returns the files matching any of the patterns, relative to the sandbox.
+ **/
+fn find_matches(agent_url: &str, client: &reqwest::Client, sandbox_path: &str, path: &str, patterns: &[Pattern], depth: u32, matches: &mut Vec<(String, u64)>) -> Result<(), String> {
+
+    for file_info in list_files(agent_url, client, path)? {
+        let relative_path = String::from(file_info.path.trim_left_matches(sandbox_path).trim_left_matches('/'));
+
+        if file_info.mode & FILE_TYPE_MASK == DIRECTORY_MODE {
+
+            if depth < MAX_DEPTH {
+                find_matches(agent_url, client, sandbox_path, file_info.path.as_str(), patterns, depth + 1, matches)?;
+            }
+
+        } else if patterns.iter().any(|pattern| pattern.matches(relative_path.as_str())) {
+            matches.push((relative_path, file_info.size));
+        }
+
+    }
+
+    Ok(())
+}
+
+fn download_file(agent_url: &str, client: &reqwest::Client, remote_path: &str, size: u64, local_path: &Path) -> Result<(), String> {
+
+    if let Some(parent) = local_path.parent() {
+
+        if let Err(e) = fs::create_dir_all(parent) {
+            return Err(format!("Unable to create {}: {}", parent.display(), e));
+        }
+
+    }
+
+    let mut file = match File::create(local_path) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("Unable to create {}: {}", local_path.display(), e))
+    };
+
+    let mut offset: u64 = 0;
+
+    while offset < size {
+
+        let request = types::ReadFileRequest {
+            request_type: String::from("READ_FILE"),
+            read_file: types::ReadFileRequestData {
+                path: String::from(remote_path),
+                offset: offset,
+                length: READ_SIZE
+            }
+        };
+
+        let body_content = serde_json::to_string(&request).unwrap();
+
+        let response = match network::post(client, agent_url, body_content).and_then(|mut response| network::read_json::<types::ReadFileResponse>(&mut response)) {
+            Ok(response) => response,
+            Err(e) => return Err(format!("Unable to read {}: {}", remote_path, e))
+        };
+
+        let data: Vec<u8> = match decode(response.read_file.data.as_str()) {
+            Ok(data) => data,
+            Err(e) => return Err(format!("Unable to decode {}: {}", remote_path, e))
+        };
+
+        if data.is_empty() {
+            // The file shrunk since it was listed.
+            break;
+        }
+
+        if let Err(e) = file.write_all(&data[..]) {
+            return Err(format!("Unable to write {}: {}", local_path.display(), e));
+        }
+
+        offset += data.len() as u64;
+    }
+
+    Ok(())
+}
+
+/**
+ * Downloads every file in the sandbox matching one of the patterns into the collect directory, keeping the
+ * layout of the sandbox.  Failures are reported but don't stop the remaining files from being downloaded.
+ * Returns the number of files downloaded.
+ **/
+pub fn collect(agent_url: &str, client: &reqwest::Client, sandbox_path: &str, patterns: &[Pattern], collect_dir: &str, verbose: bool) -> usize {
+    let mut matches: Vec<(String, u64)> = vec![];
+
+    if let Err(e) = find_matches(agent_url, client, sandbox_path, sandbox_path, patterns, 0, &mut matches) {
+        error!("Unable to collect artifacts: {}", e);
+        return 0;
+    }
+
+    let mut collected = 0;
+
+    for (relative_path, size) in matches {
+        let remote_path = format!("{}/{}", sandbox_path, relative_path);
+        let local_path = Path::new(collect_dir).join(relative_path.as_str());
+
+        if verbose {
+            println!("Collecting {} ({} bytes)", relative_path, size);
+        }
+
+        match download_file(agent_url, client, remote_path.as_str(), size, local_path.as_path()) {
+            Ok(_) => collected += 1,
+            Err(e) => error!("{}", e)
+        };
+
+    }
+
+    if collected == 0 {
+        warn!("No files in the sandbox matched the --collect patterns");
+    }
+
+    collected
+}
@@ -1,12 +1,13 @@
 use base64::decode;
+use events::EventLog;
 use hyper::header::{Accept, ContentType, Headers};
 use network;
+use output;
 use reqwest;
 use serde_json;
 use serde_json::Value;
+use std::cmp;
 use std::io::Read;
-use std::io::stderr;
-use std::io::stdout;
 use std::io::Write;
 use std::num::Wrapping;
 use std::sync::{Arc, Mutex};
@@ -15,6 +16,11 @@ use std::thread;
 use std::time;
 use types;
 
+const MIN_POLL_MILLIS: u64 = 50;
+const MAX_POLL_MILLIS: u64 = 1000;
+// Attempts to read the rest of an output file after the task has finished before giving up on it.
+const MAX_DRAIN_FAILURES: u32 = 5;
+
 /**
  * Makes use of the Mesos Operator API to stream STDIN and STDOUT to the running container.
  */
@@ -42,167 +48,266 @@ pub struct InteractiveConsole {
     state: ConsoleState
 }
 
-fn attach_container_output(agent_url: String, container_id: String, mut stdout: Box<Write + Send>, mut stderr: Option<Box<Write + Send>>, running_state: Arc<Mutex<bool>>) -> thread::JoinHandle<()> {
-
-    thread::spawn(move || {
+fn open_container_output(agent_url: &str, client: &reqwest::Client, container_id: &str) -> Result<reqwest::Response, network::RequestError> {
 
-        let request = types::AttachContainerOutputRequest {
-            request_type: String::from("ATTACH_CONTAINER_OUTPUT"),
-            attach_container_output: types::AttachContainerOutput {
-                container_id: types::ValueContainer {
-                    value: container_id.clone()
-                }
+    let request = types::AttachContainerOutputRequest {
+        request_type: String::from("ATTACH_CONTAINER_OUTPUT"),
+        attach_container_output: types::AttachContainerOutput {
+            container_id: types::ValueContainer {
+                value: String::from(container_id)
             }
-        };
+        }
+    };
 
-        let body_content = serde_json::to_string(&request).unwrap();
-        let mut headers = Headers::new();
+    let body_content = serde_json::to_string(&request).unwrap();
 
-        headers.set(ContentType::json());
+    network::post(client, agent_url, body_content)
+}
 
-        let client: reqwest::Client = reqwest::ClientBuilder::new()
-            .default_headers(headers)
-            .build().unwrap();
+fn stream_container_output(mut response: reqwest::Response, mut stdout: Box<Write + Send>, mut stderr: Option<Box<Write + Send>>, running_state: Arc<Mutex<bool>>) -> thread::JoinHandle<()> {
 
-        let url = agent_url.as_str();
+    thread::spawn(move || {
 
-        println!("Body: {}", body_content);
+        loop {
+            let message_content = match network::read_next_message(&mut response) {
+                Ok(message_content) => message_content,
+                Err(e) => {
 
-        match client.post(url)
-            .body(body_content)
-            .send() {
+                    // The agent closes the stream once the container exits.
+                    if *running_state.lock().unwrap() {
+                        info!("Container output stream closed: {}", e);
+                    }
 
-            Ok(mut response) => {
+                    break;
+                }
+            };
 
-                loop {
-                    let message_content = network::read_next_message(&mut response);
-                    let message: types::AttachContainerOutputMessage = serde_json::from_str(message_content.as_str()).unwrap();
+            let message: types::AttachContainerOutputMessage = serde_json::from_str(message_content.as_str()).unwrap();
 
-                    match message.message_type.as_str() {
+            match message.message_type.as_str() {
 
-                        "DATA" => {
+                "DATA" => {
 
-                            match message.data.output_type.as_str() {
+                    match message.data.output_type.as_str() {
 
-                                "STDOUT" => {
-                                    let data: Vec<u8> = decode(message.data.data.as_str()).unwrap();
-                                    stdout.write(&data[..]);
-                                },
-                                "STDERR" => {
+                        "STDOUT" => {
+                            let data: Vec<u8> = decode(message.data.data.as_str()).unwrap();
+
+                            if let Err(e) = stdout.write_all(&data[..]) {
+                                error!("Unable to write output: {}", e);
+                            }
+
+                        },
+                        "STDERR" => {
 
-                                    match stderr {
+                            match stderr {
 
-                                        Some(ref mut writer) => {
-                                            let data: Vec<u8> = decode(message.data.data.as_str()).unwrap();
-                                            writer.write(&data[..]);
-                                        },
-                                        None => {
-                                            // Ignoring stderr.
-                                        }
+                                Some(ref mut writer) => {
+                                    let data: Vec<u8> = decode(message.data.data.as_str()).unwrap();
 
+                                    if let Err(e) = writer.write_all(&data[..]) {
+                                        error!("Unable to write output: {}", e);
                                     }
 
                                 },
-                                _ => {
-                                    error!("Unknown data output: {}", message.data.output_type);
+                                None => {
+                                    // Ignoring stderr.
                                 }
 
                             }
 
                         },
                         _ => {
-                            error!("Unhandled message type of {}", message.message_type)
+                            error!("Unknown data output: {}", message.data.output_type);
                         }
 
                     }
 
-                    let running = running_state.lock().unwrap();
+                },
+                _ => {
+                    error!("Unhandled message type of {}", message.message_type)
+                }
 
-                    if !*running {
-                        break;
-                    }
+            }
 
-                }
+        }
 
-            },
-            Err(e) => {
-                println!("Uhoh!");
-                error!("{}", e);
+        let _ = stdout.flush();
+
+        if let Some(ref mut writer) = stderr {
+            let _ = writer.flush();
+        }
+
+    })
+}
+
+fn attach_container_output(agent_url: String, client: reqwest::Client, container_id: String, stdout: Box<Write + Send>, stderr: Option<Box<Write + Send>>, running_state: Arc<Mutex<bool>>) -> Option<thread::JoinHandle<()>> {
+
+    match open_container_output(agent_url.as_str(), &client, container_id.as_str()) {
+        Ok(response) => Some(stream_container_output(response, stdout, stderr, running_state)),
+        Err(e) => {
+            error!("Unable to attach to container output: {}", e);
+            None
+        }
+    }
+
+}
+
+/**
+ * Size of a sandbox file, from a READ_FILE that doesn't read any data.
+ **/
+fn sandbox_file_size(agent_url: &str, client: &reqwest::Client, path: &str) -> Result<u64, String> {
+
+    let request = types::ReadFileRequest {
+        request_type: String::from("READ_FILE"),
+        read_file: types::ReadFileRequestData {
+            path: String::from(path),
+            offset: 0,
+            length: 0
+        }
+    };
+
+    let body_content = serde_json::to_string(&request).unwrap();
+
+    match network::post(client, agent_url, body_content).and_then(|mut response| network::read_json::<types::ReadFileResponse>(&mut response)) {
+        Ok(response) => Ok(response.read_file.size),
+        Err(e) => Err(format!("{}", e))
+    }
+
+}
+
+/**
+ * Copies what the task wrote to a sandbox file before we attached to its output.  end is the size of the file
+ * taken before attaching, everything after it comes from the attached stream.
+ **/
+fn read_backlog(agent_url: &str, client: &reqwest::Client, path: &str, chunk_size: u64, end: u64, writer: &mut Box<Write + Send>) {
+    let mut offset: u64 = 0;
+
+    while offset < end {
+
+        let request = types::ReadFileRequest {
+            request_type: String::from("READ_FILE"),
+            read_file: types::ReadFileRequestData {
+                path: String::from(path),
+                offset,
+                length: cmp::min(chunk_size, end - offset)
             }
+        };
 
+        let body_content = serde_json::to_string(&request).unwrap();
+
+        let response = match network::post(client, agent_url, body_content).and_then(|mut response| network::read_json::<types::ReadFileResponse>(&mut response)) {
+            Ok(response) => response,
+            Err(e) => {
+                warn!("Unable to read the output written to {} before attaching: {}", path, e);
+                return;
+            }
         };
 
-    })
+        let mut data: Vec<u8> = decode(response.read_file.data.as_str()).unwrap();
+
+        data.truncate((end - offset) as usize);
+
+        if data.is_empty() {
+            break;
+        }
+
+        if let Err(e) = writer.write_all(&data[..]) {
+            error!("Unable to write output of {}: {}", path, e);
+            return;
+        }
+
+        offset += data.len() as u64;
+    }
+
 }
 
-fn create_data_file_read_thread(agent_url: String, sandbox_path: String, mut writer: Box<Write + Send>, running_state: Arc<Mutex<bool>>) -> thread::JoinHandle<()> {
+/**
+ * Tails a file in the sandbox with READ_FILE.  Polls quickly while the file is growing and backs off to
+ * MAX_POLL_MILLIS while it is idle.  Once the console is finished it keeps reading until it has caught up
+ * with the final size of the file reported by the agent.
+ **/
+fn create_data_file_read_thread(agent_url: String, client: reqwest::Client, sandbox_path: String, chunk_size: u64, mut writer: Box<Write + Send>, running_state: Arc<Mutex<bool>>) -> thread::JoinHandle<()> {
 
     thread::spawn(move || {
-        let read_size: u32 = 100000; // Read in 100kB chunks
-        let mut offset: u32 = 0;
-        let mut last_read_size: u32 = 0;
-        let mut last_running: bool = false;
+        let mut offset: u64 = 0;
+        let mut file_size: u64 = 0;
+        let mut poll_millis: u64 = MIN_POLL_MILLIS;
+        let mut drain_failures: u32 = 0;
 
         loop {
-            {
-                let running = running_state.lock().unwrap();
+            // Checked before reading so the last read always happens after the task has finished.
+            let running = *running_state.lock().unwrap();
+
+            let request = types::ReadFileRequest {
+                request_type: String::from("READ_FILE"),
+                read_file: types::ReadFileRequestData {
+                    path: sandbox_path.clone(),
+                    offset: offset,
+                    length: chunk_size
+                }
+            };
 
-                if *running || last_running || last_read_size > 0 {
+            let body_content = serde_json::to_string(&request).unwrap();
+            let url = agent_url.as_str();
 
-                    let request = types::ReadFileRequest {
-                        request_type: String::from("READ_FILE"),
-                        read_file: types::ReadFileRequestData {
-                            path: sandbox_path.clone(),
-                            offset: offset,
-                            length: read_size
-                        }
-                    };
+            let result = network::post(&client, url, body_content)
+                .and_then(|mut response| network::read_json::<types::ReadFileResponse>(&mut response));
 
-                    let body_content = serde_json::to_string(&request).unwrap();
+            let read_size: u64 = match result {
 
-                    let mut headers = Headers::new();
+                Ok(response) => {
+                    let data: Vec<u8> = decode(response.read_file.data.as_str()).unwrap();
 
-                    headers.set(ContentType::json());
+                    // Keep reading so any writers that still work get the rest of the output.
+                    if let Err(e) = writer.write_all(&data[..]) {
+                        error!("Unable to write output of {}: {}", sandbox_path, e);
+                    }
 
-                    let client: reqwest::Client = reqwest::ClientBuilder::new()
-                        .default_headers(headers)
-                        .build().unwrap();
+                    file_size = response.read_file.size;
+                    offset += data.len() as u64;
 
-                    let url = agent_url.as_str();
+                    data.len() as u64
+                },
+                Err(e) => {
+                    error!("Error reading from agent: {}", e);
 
-                    match client.post(url)
-                        .body(body_content)
-                        .send() {
+                    0
+                }
 
-                        Ok(ref mut response) => {
-                            let response_content = response.text().unwrap();
-                            let response: types::ReadFileResponse = serde_json::from_str(response_content.as_str()).unwrap();
-                            let data: Vec<u8> = decode(response.read_file.data.as_str()).unwrap();
+            };
 
-                            last_read_size = data.len() as u32;
-                            writer.write(&data[..]);
+            if !running {
 
-                            offset = offset.wrapping_add(last_read_size);
-                        },
-                        Err(e) => {
-                            error!("Error reading from agent: {}", e);
-                        }
+                if read_size == 0 && offset >= file_size {
+                    break;
+                }
 
-                    };
+                if read_size == 0 {
+                    drain_failures += 1;
+                }
 
-                } else {
+                if drain_failures >= MAX_DRAIN_FAILURES {
+                    warn!("Output of {} is truncated.  Read {} of {} bytes", sandbox_path, offset, file_size);
                     break;
                 }
 
-                last_running = *running;
             }
 
-            if last_read_size == 0 {
-                thread::sleep(time::Duration::from_millis(1000));
+            if read_size == 0 {
+                thread::sleep(time::Duration::from_millis(poll_millis));
+                poll_millis = cmp::min(poll_millis * 2, MAX_POLL_MILLIS);
+
+            } else {
+                poll_millis = MIN_POLL_MILLIS;
             }
 
         }
 
+        if let Err(e) = writer.flush() {
+            error!("Unable to flush output of {}: {}", sandbox_path, e);
+        }
+
     })
 
 }
@@ -240,44 +345,136 @@ impl Console for InteractiveConsole {
 
 impl HeadlessConsole {
 
-    pub fn new(agent_url: &str, sandbox_path: &str, show_stderr: bool) -> HeadlessConsole {
+    pub fn new(agent_url: &str, sandbox_path: &str, container_id: &str, task_info: &types::RequestedTaskInfo, events: &EventLog, context: &output::LineContext) -> HeadlessConsole {
+        let (stdout_writer, stderr_writer) = output::create_writers(&task_info.output, task_info.stderr, events, context);
+
+        HeadlessConsole::open(
+            agent_url,
+            sandbox_path,
+            container_id,
+            &task_info.follow_mode,
+            task_info.chunk_size,
+            &task_info.http,
+            stdout_writer,
+            stderr_writer
+        )
+    }
+
+    /**
+     * Follows the output of a container into the given writers.  stderr is only read when it has a writer.
+     **/
+    pub fn open(agent_url: &str, sandbox_path: &str, container_id: &str, follow_mode: &types::FollowMode, chunk_size: u64, http: &types::HttpConfig, mut stdout_writer: Box<Write + Send>, mut stderr_writer: Option<Box<Write + Send>>) -> HeadlessConsole {
         let running = Arc::new(Mutex::new(true));
-        let mut stdout_path = String::from(sandbox_path);
-        let mut stderr_path = String::from(sandbox_path);
-        let stdout_running = Arc::clone(&running);
-        let stderr_running = Arc::clone(&running);
+        let client = network::create_json_client(http);
+        let stdout_path = format!("{}/stdout", sandbox_path);
+        let stderr_path = format!("{}/stderr", sandbox_path);
+
+        // Taken before attaching so output written while the stream is opened isn't read from both.
+        let backlog_sizes = match *follow_mode {
+            types::FollowMode::Poll => None,
+            _ => {
+                let stdout_size = sandbox_file_size(agent_url, &client, stdout_path.as_str());
+                let stderr_size = match stderr_writer {
+                    Some(_) => sandbox_file_size(agent_url, &client, stderr_path.as_str()),
+                    None => Ok(0)
+                };
+
+                Some((stdout_size, stderr_size))
+            }
+        };
 
-        stdout_path.push_str("/stdout");
-        stderr_path.push_str("/stderr");
+        let attach_response = match *follow_mode {
+            types::FollowMode::Poll => None,
+            ref mode => {
+                let mut headers = Headers::new();
 
-        HeadlessConsole {
-            agent_url: String::from(agent_url),
-            sandbox_path: String::from(sandbox_path),
-            state: ConsoleState {
-                running,
-                stdout_thread: Some(
-                    create_data_file_read_thread(
-                        String::from(agent_url),
-                        stdout_path,
-                        Box::new(stdout()),
-                        stdout_running
-                    )
-                ),
-                stdin_thread: None,
-                stderr_thread: match show_stderr {
-                    true => Some(
+                headers.set(ContentType::json());
+                headers.set(Accept::json());
+
+                let client = network::create_streaming_client(http, headers);
+
+                match open_container_output(agent_url, &client, container_id) {
+                    Ok(response) => Some(response),
+                    Err(e) => {
+
+                        if *mode == types::FollowMode::Attach {
+                            error!("Unable to attach to container output: {}", e);
+                            ::std::process::exit(1);
+                        }
+
+                        info!("Unable to attach to container output, reading the sandbox instead: {}", e);
+                        None
+                    }
+                }
+            }
+        };
+
+        let state = match attach_response {
+
+            Some(response) => {
+                // The attached stream only carries output written from now on, so catch up from the sandbox first.
+                if let Some((stdout_size, stderr_size)) = backlog_sizes {
+
+                    match stdout_size {
+                        Ok(size) => read_backlog(agent_url, &client, stdout_path.as_str(), chunk_size, size, &mut stdout_writer),
+                        Err(e) => warn!("Unable to read the output written to {} before attaching: {}", stdout_path, e)
+                    };
+
+                    if let Some(ref mut writer) = stderr_writer {
+
+                        match stderr_size {
+                            Ok(size) => read_backlog(agent_url, &client, stderr_path.as_str(), chunk_size, size, writer),
+                            Err(e) => warn!("Unable to read the output written to {} before attaching: {}", stderr_path, e)
+                        };
+
+                    }
+
+                }
+
+                ConsoleState {
+                    running: Arc::clone(&running),
+                    stdout_thread: Some(stream_container_output(response, stdout_writer, stderr_writer, Arc::clone(&running))),
+                    stdin_thread: None,
+                    stderr_thread: None
+                }
+            },
+            None => {
+                // The client is shared by the stdout and stderr readers so they reuse the same keep-alive connections.
+                ConsoleState {
+                    running: Arc::clone(&running),
+                    stdout_thread: Some(
                         create_data_file_read_thread(
                             String::from(agent_url),
-                            stderr_path,
-                            Box::new(stderr()),
-                            stderr_running
+                            client.clone(),
+                            stdout_path,
+                            chunk_size,
+                            stdout_writer,
+                            Arc::clone(&running)
                         )
                     ),
-                    false => None
+                    stdin_thread: None,
+                    stderr_thread: match stderr_writer {
+                        Some(writer) => Some(
+                            create_data_file_read_thread(
+                                String::from(agent_url),
+                                client.clone(),
+                                stderr_path,
+                                chunk_size,
+                                writer,
+                                Arc::clone(&running)
+                            )
+                        ),
+                        None => None
+                    }
                 }
-
             }
 
+        };
+
+        HeadlessConsole {
+            agent_url: String::from(agent_url),
+            sandbox_path: String::from(sandbox_path),
+            state
         }
 
     }
@@ -295,7 +492,13 @@ impl ConsoleState {
 
 impl InteractiveConsole {
 
-    pub fn new(agent_url: &str, container_id: &str, show_stderr: bool) -> InteractiveConsole {
+    pub fn new(agent_url: &str, container_id: &str, task_info: &types::RequestedTaskInfo, events: &EventLog, context: &output::LineContext) -> InteractiveConsole {
+        let mut headers = Headers::new();
+        let (stdout_writer, stderr_writer) = output::create_writers(&task_info.output, task_info.stderr, events, context);
+
+        headers.set(ContentType::json());
+
+        let client = network::create_streaming_client(&task_info.http, headers);
         let running = Arc::new(Mutex::new(true));
         let thread_running = Arc::clone(&running);
 
@@ -304,17 +507,13 @@ impl InteractiveConsole {
             container_id: String::from(container_id),
             state: ConsoleState {
                 running,
-                stdout_thread: Some(
-                    attach_container_output(
-                        String::from(agent_url),
-                        String::from(container_id),
-                        Box::new(stdout()),
-                        match show_stderr {
-                            true => Some(Box::new(stderr())),
-                            false => None
-                        },
-                        thread_running
-                    )
+                stdout_thread: attach_container_output(
+                    String::from(agent_url),
+                    client.clone(),
+                    String::from(container_id),
+                    stdout_writer,
+                    stderr_writer,
+                    thread_running
                 ),
                 stdin_thread: None,
                 stderr_thread: None
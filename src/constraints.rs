@@ -0,0 +1,541 @@
+use network;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use types::{AttributeValue, HttpConfig};
+
+/**
+ * Placement constraints evaluated against the attributes of an agent.
+ *
+ * Each -a option is a constraint group.  Groups are ANDed together and the alternatives within a group
+ * (separated by ||) are ORed.  An alternative is either the short form used by earlier versions of RExe
+ *
+ *     attribute=value, attribute=/regex/, attribute!=value, attribute>=1.5, attribute<4 ...
+ *
+ * or a Marathon style constraint
+ *
+ *     attribute:OPERATOR[:value]
+ *
+ * where OPERATOR is one of IS, LIKE, UNLIKE, IN, NOT_IN, GROUP_BY, MAX_PER, LT, LE, GT, GE or EXISTS.
+ **/
+
+pub enum Operator {
+    Is(String),
+    IsNot(String),
+    Like(Regex),
+    Unlike(Regex),
+    In(Vec<String>),
+    NotIn(Vec<String>),
+    GroupBy(Option<u32>),
+    MaxPer(u32),
+    LessThan(f64),
+    LessOrEqual(f64),
+    GreaterThan(f64),
+    GreaterOrEqual(f64),
+    Exists
+}
+
+pub struct Constraint {
+    pub attribute: String,
+    pub operator: Operator,
+    pub expression: String
+}
+
+pub struct ConstraintGroup {
+    pub alternatives: Vec<Constraint>,
+    pub expression: String
+}
+
+pub struct Constraints {
+    pub groups: Vec<ConstraintGroup>
+}
+
+/**
+ * Knowledge about the tasks other RExe invocations are already running in the cluster.  GROUP_BY and MAX_PER
+ * are the only operators that need this.
+ **/
+pub struct ConstraintContext {
+    // attribute name -> attribute value -> number of active RExe tasks on agents with that value.
+    task_counts: HashMap<String, HashMap<String, u32>>,
+    // attribute name -> every value of that attribute seen across the cluster.
+    known_values: HashMap<String, HashSet<String>>
+}
+
+impl fmt::Display for Constraint {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+
+}
+
+fn parse_number(value: &str, expression: &str) -> Result<f64, String> {
+
+    match value.trim().parse::<f64>() {
+        Ok(number) => Ok(number),
+        Err(_) => Err(format!("'{}' in '{}' is not a number", value, expression))
+    }
+
+}
+
+fn parse_list(value: &str) -> Vec<String> {
+    value.split(',').map(|item| String::from(item.trim())).filter(|item| !item.is_empty()).collect()
+}
+
+fn parse_regex(pattern: &str, anchored: bool, expression: &str) -> Result<Regex, String> {
+    let full_pattern = match anchored {
+        true => format!("^(?:{})$", pattern),
+        false => String::from(pattern)
+    };
+
+    match Regex::new(full_pattern.as_str()) {
+        Ok(regex) => Ok(regex),
+        Err(e) => Err(format!("Invalid pattern in '{}': {}", expression, e))
+    }
+
+}
+
+fn parse_marathon(attribute: &str, operator: &str, value: Option<&str>, expression: &str) -> Result<Operator, String> {
+    let operator_name = operator.trim().to_uppercase().replace(" ", "_");
+
+    let required: Result<&str, String> = match value {
+        Some(value) if !value.is_empty() => Ok(value),
+        _ => Err(format!("Operator {} in '{}' requires a value", operator_name, expression))
+    };
+
+    let parsed = match operator_name.as_str() {
+        "IS" | "EQ" => Operator::Is(String::from(required.clone()?)),
+        "LIKE" => Operator::Like(parse_regex(required.clone()?, true, expression)?),
+        "UNLIKE" => Operator::Unlike(parse_regex(required.clone()?, true, expression)?),
+        "IN" => Operator::In(parse_list(required.clone()?)),
+        "NOT_IN" => Operator::NotIn(parse_list(required.clone()?)),
+        "GROUP_BY" => match value {
+            Some(value) if !value.is_empty() => match value.trim().parse::<u32>() {
+                Ok(groups) => Operator::GroupBy(Some(groups)),
+                Err(_) => return Err(format!("GROUP_BY in '{}' expects a number of groups", expression))
+            },
+            _ => Operator::GroupBy(None)
+        },
+        "MAX_PER" => match required.clone()?.trim().parse::<u32>() {
+            Ok(limit) if limit > 0 => Operator::MaxPer(limit),
+            _ => return Err(format!("MAX_PER in '{}' expects a number greater than 0", expression))
+        },
+        "LT" => Operator::LessThan(parse_number(required.clone()?, expression)?),
+        "LE" => Operator::LessOrEqual(parse_number(required.clone()?, expression)?),
+        "GT" => Operator::GreaterThan(parse_number(required.clone()?, expression)?),
+        "GE" => Operator::GreaterOrEqual(parse_number(required.clone()?, expression)?),
+        "EXISTS" => Operator::Exists,
+        _ => return Err(format!("Unknown operator {} for attribute {}", operator.trim(), attribute))
+    };
+
+    Ok(parsed)
+}
+
+fn parse_short_form(attribute: &str, operator: &str, value: &str, expression: &str) -> Result<Operator, String> {
+
+    let parsed = match operator {
+        "=" => {
+
+            if value.len() > 1 && value.starts_with("/") && value.ends_with("/") {
+                // Unanchored to stay compatible with the attribute=/pattern/ syntax of earlier releases.
+                Operator::Like(parse_regex(value.trim_matches('/'), false, expression)?)
+
+            } else {
+                Operator::Is(String::from(value))
+            }
+
+        },
+        "!=" => {
+
+            if value.len() > 1 && value.starts_with("/") && value.ends_with("/") {
+                Operator::Unlike(parse_regex(value.trim_matches('/'), false, expression)?)
+
+            } else {
+                Operator::IsNot(String::from(value))
+            }
+
+        },
+        "<" => Operator::LessThan(parse_number(value, expression)?),
+        "<=" => Operator::LessOrEqual(parse_number(value, expression)?),
+        ">" => Operator::GreaterThan(parse_number(value, expression)?),
+        ">=" => Operator::GreaterOrEqual(parse_number(value, expression)?),
+        _ => return Err(format!("Unknown operator {} for attribute {}", operator, attribute))
+    };
+
+    Ok(parsed)
+}
+
+fn parse_constraint(expression: &str) -> Result<Constraint, String> {
+    let marathon_regex = Regex::new(r"^([^:=<>!]+):([A-Za-z_ ]+)(?::(.*))?$").unwrap();
+    let short_regex = Regex::new(r"^([^:=<>!]+?)(!=|>=|<=|=|>|<)(.+)$").unwrap();
+    let expression = expression.trim();
+
+    let (attribute, operator) = if let Some(groups) = marathon_regex.captures(expression) {
+        let attribute = groups.get(1).unwrap().as_str().trim();
+
+        (attribute, parse_marathon(
+            attribute,
+            groups.get(2).unwrap().as_str(),
+            groups.get(3).map(|value| value.as_str()),
+            expression
+        )?)
+
+    } else if let Some(groups) = short_regex.captures(expression) {
+        let attribute = groups.get(1).unwrap().as_str().trim();
+
+        (attribute, parse_short_form(
+            attribute,
+            groups.get(2).unwrap().as_str(),
+            groups.get(3).unwrap().as_str(),
+            expression
+        )?)
+
+    } else {
+        return Err(format!("Unable to parse constraint '{}'.  Expected attribute=value or attribute:OPERATOR[:value]", expression));
+    };
+
+    Ok(Constraint {
+        attribute: String::from(attribute),
+        operator,
+        expression: String::from(expression)
+    })
+}
+
+pub fn parse(expression: &str) -> Result<ConstraintGroup, String> {
+    let mut alternatives: Vec<Constraint> = vec![];
+
+    for alternative in expression.split("||") {
+        alternatives.push(parse_constraint(alternative)?);
+    }
+
+    Ok(ConstraintGroup {
+        alternatives,
+        expression: String::from(expression)
+    })
+}
+
+fn as_number(value: &AttributeValue) -> Option<f64> {
+
+    match *value {
+        AttributeValue::Scalar(number) => Some(number),
+        AttributeValue::Text(ref text) => text.parse::<f64>().ok(),
+        _ => None
+    }
+
+}
+
+fn matches_value(attribute: &AttributeValue, expected: &str) -> bool {
+
+    match *attribute {
+        AttributeValue::Text(ref text) => text == expected,
+        AttributeValue::Scalar(number) => match expected.parse::<f64>() {
+            Ok(expected) => number == expected,
+            Err(_) => false
+        },
+        AttributeValue::Ranges(ref ranges) => match expected.parse::<u64>() {
+            Ok(expected) => ranges.iter().any(|&(begin, end)| expected >= begin && expected <= end),
+            Err(_) => false
+        },
+        AttributeValue::Set(ref items) => items.iter().any(|item| item == expected)
+    }
+
+}
+
+fn matches_regex(attribute: &AttributeValue, regex: &Regex) -> bool {
+
+    match *attribute {
+        AttributeValue::Set(ref items) => items.iter().any(|item| regex.is_match(item.as_str())),
+        _ => regex.is_match(attribute.to_string().as_str())
+    }
+
+}
+
+impl Constraint {
+
+    pub fn needs_context(&self) -> bool {
+
+        match self.operator {
+            Operator::GroupBy(_) | Operator::MaxPer(_) => true,
+            _ => false
+        }
+
+    }
+
+    /**
+     * Returns Ok if the agent attributes satisfy this constraint, otherwise the reason it was rejected.
+     **/
+    pub fn check(&self, attributes: &HashMap<String, AttributeValue>, context: &ConstraintContext) -> Result<(), String> {
+        let attribute = attributes.get(&self.attribute);
+
+        let missing = || Err(format!("attribute {} is not set", self.attribute));
+
+        match self.operator {
+            Operator::Exists => match attribute {
+                Some(_) => Ok(()),
+                None => missing()
+            },
+            Operator::Is(ref expected) => match attribute {
+                Some(value) if matches_value(value, expected) => Ok(()),
+                Some(value) => Err(format!("{}={} is not {}", self.attribute, value, expected)),
+                None => missing()
+            },
+            Operator::IsNot(ref expected) => match attribute {
+                Some(value) if matches_value(value, expected) => Err(format!("{}={} must not be {}", self.attribute, value, expected)),
+                _ => Ok(())
+            },
+            Operator::Like(ref regex) => match attribute {
+                Some(value) if matches_regex(value, regex) => Ok(()),
+                Some(value) => Err(format!("{}={} does not match {}", self.attribute, value, regex)),
+                None => missing()
+            },
+            Operator::Unlike(ref regex) => match attribute {
+                Some(value) if matches_regex(value, regex) => Err(format!("{}={} matches {}", self.attribute, value, regex)),
+                _ => Ok(())
+            },
+            Operator::In(ref expected) => match attribute {
+                Some(value) if expected.iter().any(|item| matches_value(value, item)) => Ok(()),
+                Some(value) => Err(format!("{}={} is not one of {}", self.attribute, value, expected.join(","))),
+                None => missing()
+            },
+            Operator::NotIn(ref expected) => match attribute {
+                Some(value) if expected.iter().any(|item| matches_value(value, item)) => Err(format!("{}={} is one of {}", self.attribute, value, expected.join(","))),
+                _ => Ok(())
+            },
+            Operator::LessThan(limit) => self.compare(attribute, limit, "<", |value| value < limit),
+            Operator::LessOrEqual(limit) => self.compare(attribute, limit, "<=", |value| value <= limit),
+            Operator::GreaterThan(limit) => self.compare(attribute, limit, ">", |value| value > limit),
+            Operator::GreaterOrEqual(limit) => self.compare(attribute, limit, ">=", |value| value >= limit),
+            Operator::MaxPer(limit) => match attribute {
+                Some(value) => {
+                    let count = context.task_count(&self.attribute, &value.to_string());
+
+                    if count < limit {
+                        Ok(())
+
+                    } else {
+                        Err(format!("{} RExe tasks already running where {}={} (MAX_PER {})", count, self.attribute, value, limit))
+                    }
+
+                },
+                None => missing()
+            },
+            Operator::GroupBy(groups) => match attribute {
+                Some(value) => {
+                    let count = context.task_count(&self.attribute, &value.to_string());
+                    let minimum = context.minimum_task_count(&self.attribute, groups);
+
+                    if count <= minimum {
+                        Ok(())
+
+                    } else {
+                        Err(format!("{} RExe tasks already running where {}={}, other values have {}", count, self.attribute, value, minimum))
+                    }
+
+                },
+                None => missing()
+            }
+        }
+
+    }
+
+    fn compare<F>(&self, attribute: Option<&AttributeValue>, limit: f64, symbol: &str, predicate: F) -> Result<(), String> where F: Fn(f64) -> bool {
+
+        match attribute {
+            Some(value) => match as_number(value) {
+                Some(number) if predicate(number) => Ok(()),
+                Some(number) => Err(format!("{}={} is not {} {}", self.attribute, number, symbol, limit)),
+                None => Err(format!("{}={} is not a scalar", self.attribute, value))
+            },
+            None => Err(format!("attribute {} is not set", self.attribute))
+        }
+
+    }
+
+}
+
+impl ConstraintGroup {
+
+    pub fn check(&self, attributes: &HashMap<String, AttributeValue>, context: &ConstraintContext) -> Result<(), String> {
+        let mut reasons: Vec<String> = vec![];
+
+        for constraint in &self.alternatives {
+
+            match constraint.check(attributes, context) {
+                Ok(()) => return Ok(()),
+                Err(reason) => reasons.push(reason)
+            }
+
+        }
+
+        Err(format!("constraint '{}' rejected agent: {}", self.expression, reasons.join(" or ")))
+    }
+
+}
+
+impl Constraints {
+
+    pub fn new() -> Constraints {
+        Constraints { groups: vec![] }
+    }
+
+    pub fn add(&mut self, group: ConstraintGroup) {
+        self.groups.push(group);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    pub fn needs_context(&self) -> bool {
+        self.groups.iter().any(|group| group.alternatives.iter().any(|constraint| constraint.needs_context()))
+    }
+
+    pub fn check(&self, attributes: &HashMap<String, AttributeValue>, context: &ConstraintContext) -> Result<(), String> {
+
+        for group in &self.groups {
+            group.check(attributes, context)?;
+        }
+
+        Ok(())
+    }
+
+}
+
+/**
+ * Converts an attribute from the master's /state endpoint, which flattens attributes into plain JSON values.
+ **/
+fn state_attribute_value(value: &Value) -> AttributeValue {
+
+    match *value {
+        Value::Number(ref number) => AttributeValue::Scalar(number.as_f64().unwrap_or(0.0)),
+        Value::String(ref text) => AttributeValue::Text(text.clone()),
+        _ => AttributeValue::Text(value.to_string())
+    }
+
+}
+
+impl ConstraintContext {
+
+    pub fn empty() -> ConstraintContext {
+
+        ConstraintContext {
+            task_counts: HashMap::new(),
+            known_values: HashMap::new()
+        }
+
+    }
+
+    /**
+     * Counts the active tasks launched by RExe frameworks per attribute value using the master /state endpoint.
+     **/
+    pub fn fetch(master_url: &str, http: &HttpConfig, framework_name: &str) -> Result<ConstraintContext, String> {
+        let mut state_url = String::from(master_url);
+        state_url.push_str("/state");
+
+        let client = network::create_json_client(http);
+
+        let state: Value = match network::get(&client, state_url.as_str()).and_then(|mut response| network::read_json(&mut response)) {
+            Ok(state) => state,
+            Err(e) => return Err(format!("Unable to get master state: {}", e))
+        };
+
+        let mut agent_attributes: HashMap<String, HashMap<String, String>> = HashMap::new();
+        let mut context = ConstraintContext::empty();
+
+        if let Some(agents) = state["slaves"].as_array() {
+
+            for agent in agents {
+                let mut attributes: HashMap<String, String> = HashMap::new();
+
+                if let Some(values) = agent["attributes"].as_object() {
+
+                    for (name, value) in values {
+                        attributes.insert(name.clone(), state_attribute_value(value).to_string());
+                    }
+
+                }
+
+                if !attributes.contains_key("hostname") {
+                    attributes.insert(String::from("hostname"), String::from(agent["hostname"].as_str().unwrap_or("")));
+                }
+
+                for (name, value) in &attributes {
+                    context.known_values.entry(name.clone()).or_insert(HashSet::new()).insert(value.clone());
+                }
+
+                if let Some(agent_id) = agent["id"].as_str() {
+                    agent_attributes.insert(String::from(agent_id), attributes);
+                }
+
+            }
+
+        }
+
+        if let Some(frameworks) = state["frameworks"].as_array() {
+
+            for framework in frameworks {
+
+                if framework["name"].as_str() != Some(framework_name) {
+                    continue;
+                }
+
+                if let Some(tasks) = framework["tasks"].as_array() {
+
+                    for task in tasks {
+
+                        match task["state"].as_str() {
+                            Some("TASK_STAGING") | Some("TASK_STARTING") | Some("TASK_RUNNING") => {},
+                            _ => continue
+                        };
+
+                        let agent_id = task["slave_id"].as_str().unwrap_or("");
+
+                        if let Some(attributes) = agent_attributes.get(agent_id) {
+
+                            for (name, value) in attributes {
+                                let counts = context.task_counts.entry(name.clone()).or_insert(HashMap::new());
+                                *counts.entry(value.clone()).or_insert(0) += 1;
+                            }
+
+                        }
+
+                    }
+
+                }
+
+            }
+
+        }
+
+        Ok(context)
+    }
+
+    fn task_count(&self, attribute: &str, value: &str) -> u32 {
+
+        match self.task_counts.get(attribute) {
+            Some(counts) => *counts.get(value).unwrap_or(&0),
+            None => 0
+        }
+
+    }
+
+    fn minimum_task_count(&self, attribute: &str, groups: Option<u32>) -> u32 {
+        let known = match self.known_values.get(attribute) {
+            Some(values) => values.clone(),
+            None => HashSet::new()
+        };
+
+        // If we know of fewer values than the number of groups requested, an unused value is still to come.
+        if let Some(groups) = groups {
+
+            if (known.len() as u32) < groups {
+                return 0;
+            }
+
+        }
+
+        known.iter().map(|value| self.task_count(attribute, value)).min().unwrap_or(0)
+    }
+
+}
@@ -0,0 +1,203 @@
+use chrono::Utc;
+use serde_json::{Map, Value};
+use std::fs::OpenOptions;
+use std::io;
+use std::io::{stderr, Write};
+use std::process::exit;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use types::{EventConfig, OutputFormat};
+
+/**
+ * Newline delimited JSON events for --output-format json.  Each event is written as a single line with its
+ * type, a timestamp and the milliseconds since RExe started.  Nothing is written in the text format.
+ **/
+
+struct EventState {
+    sink: Box<Write + Send>,
+    // When each kind of event, and each task state, was first seen.
+    timings: Map<String, Value>,
+    last_task_state: Option<String>
+}
+
+#[derive(Clone)]
+pub struct EventLog {
+    state: Option<Arc<Mutex<EventState>>>,
+    started: Instant,
+    include_output: bool
+}
+
+/**
+ * Turns output chunks into output events.
+ **/
+pub struct OutputEventWriter {
+    events: EventLog,
+    stream: &'static str
+}
+
+#[cfg(unix)]
+fn open_fd(fd: i32) -> io::Result<Box<Write + Send>> {
+    use std::fs::File;
+    use std::os::unix::io::{FromRawFd, IntoRawFd};
+
+    let file = unsafe { File::from_raw_fd(fd) };
+
+    // Fails with EBADF if the fd isn't open.  The fd isn't ours to close so it's released rather than dropped.
+    if let Err(e) = file.metadata() {
+        file.into_raw_fd();
+        return Err(e);
+    }
+
+    Ok(Box::new(file))
+}
+
+#[cfg(not(unix))]
+fn open_fd(_fd: i32) -> io::Result<Box<Write + Send>> {
+    Err(io::Error::new(io::ErrorKind::Other, "file descriptors are only supported on unix"))
+}
+
+fn elapsed_millis(started: &Instant) -> u64 {
+    let elapsed = started.elapsed();
+
+    elapsed.as_secs() * 1000 + (elapsed.subsec_nanos() / 1000000) as u64
+}
+
+impl EventLog {
+
+    pub fn open(config: &EventConfig) -> EventLog {
+
+        if config.format == OutputFormat::Text {
+
+            return EventLog {
+                state: None,
+                started: Instant::now(),
+                include_output: false
+            };
+
+        }
+
+        let sink: io::Result<Box<Write + Send>> = match (config.fd, config.file.as_ref()) {
+            (Some(fd), _) => open_fd(fd),
+            (None, Some(path)) => OpenOptions::new().create(true).append(true).open(path).map(|file| Box::new(file) as Box<Write + Send>),
+            (None, None) => Ok(Box::new(stderr()))
+        };
+
+        match sink {
+            Ok(sink) => EventLog {
+                state: Some(Arc::new(Mutex::new(EventState {
+                    sink,
+                    timings: Map::new(),
+                    last_task_state: None
+                }))),
+                started: Instant::now(),
+                include_output: config.include_output
+            },
+            Err(e) => {
+                error!("Unable to open the event output: {}", e);
+                exit(1);
+            }
+        }
+
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.state.is_some()
+    }
+
+    pub fn includes_output(&self) -> bool {
+        self.is_enabled() && self.include_output
+    }
+
+    /**
+     * Writes an event.  fields must be a JSON object, which the event type and timings are added to.
+     **/
+    pub fn emit(&self, event_type: &str, fields: Value) {
+
+        let state = match self.state {
+            Some(ref state) => state,
+            None => return
+        };
+
+        let elapsed = elapsed_millis(&self.started);
+
+        let mut event = match fields {
+            Value::Object(map) => map,
+            _ => Map::new()
+        };
+
+        event.insert(String::from("event"), Value::from(event_type));
+        event.insert(String::from("timestamp"), Value::from(Utc::now().to_rfc3339()));
+        event.insert(String::from("elapsed_ms"), Value::from(elapsed));
+
+        let mut state = state.lock().unwrap();
+
+        let timing_key = match event.get("state").and_then(|value| value.as_str()) {
+            Some(task_state) if event_type == "task_state" => String::from(task_state),
+            _ => String::from(event_type)
+        };
+
+        if event_type != "output" && !state.timings.contains_key(&timing_key) {
+            state.timings.insert(timing_key, Value::from(elapsed));
+        }
+
+        if event_type == "task_state" {
+            state.last_task_state = event.get("state").and_then(|value| value.as_str()).map(String::from);
+        }
+
+        let line = Value::Object(event).to_string();
+
+        if let Err(e) = writeln!(state.sink, "{}", line).and_then(|_| state.sink.flush()) {
+            warn!("Unable to write event: {}", e);
+        }
+
+    }
+
+    /**
+     * The final event, written just before RExe exits.
+     **/
+    pub fn result(&self, exit_code: i32) {
+
+        let (timings, last_task_state) = match self.state {
+            Some(ref state) => {
+                let state = state.lock().unwrap();
+                (state.timings.clone(), state.last_task_state.clone())
+            },
+            None => return
+        };
+
+        self.emit("result", json!({
+            "exit_code": exit_code,
+            "task_state": last_task_state,
+            "duration_ms": elapsed_millis(&self.started),
+            "timings_ms": timings
+        }));
+
+    }
+
+}
+
+impl OutputEventWriter {
+
+    pub fn new(events: EventLog, stream: &'static str) -> OutputEventWriter {
+        OutputEventWriter { events, stream }
+    }
+
+}
+
+impl Write for OutputEventWriter {
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+
+        self.events.emit("output", json!({
+            "stream": self.stream,
+            "data": String::from_utf8_lossy(buf)
+        }));
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+}
@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use types;
+
+/**
+ * Keeps track of why offers were rejected so we can tell the user what their task is waiting for.
+ **/
+
+pub struct Rejection {
+    pub reason: String,
+    // How far off the offer was, relative to what was requested.  Constraint failures count as a full miss.
+    pub shortfall: f32
+}
+
+struct AgentRecord {
+    hostname: String,
+    offers: u32,
+    cpus: f32,
+    gpus: i32,
+    mem: f32,
+    disk: f32,
+    rejections: Vec<Rejection>
+}
+
+pub struct OfferLog {
+    agents: HashMap<String, AgentRecord>,
+    total_offers: u32
+}
+
+impl Rejection {
+
+    pub fn constraint(reason: String) -> Rejection {
+        Rejection { reason, shortfall: 1.0 }
+    }
+
+    pub fn resource(name: &str, offered: f32, required: f32) -> Rejection {
+
+        Rejection {
+            reason: format!("insufficient {}: offered {}, required {}", name, offered, required),
+            shortfall: (required - offered) / required
+        }
+
+    }
+
+}
+
+impl AgentRecord {
+
+    fn score(&self) -> f32 {
+        self.rejections.iter().map(|rejection| rejection.shortfall).sum()
+    }
+
+}
+
+impl OfferLog {
+
+    pub fn new() -> OfferLog {
+
+        OfferLog {
+            agents: HashMap::new(),
+            total_offers: 0
+        }
+
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.agents.is_empty()
+    }
+
+    /**
+     * Records the outcome of evaluating an offer.  Only the most recent offer from each agent is kept.
+     **/
+    pub fn record(&mut self, offer: &types::Offer, rejections: Vec<Rejection>) {
+        self.total_offers += 1;
+
+        let record = self.agents.entry(offer.agent_id.clone()).or_insert(AgentRecord {
+            hostname: offer.hostname.clone(),
+            offers: 0,
+            cpus: 0.0,
+            gpus: 0,
+            mem: 0.0,
+            disk: 0.0,
+            rejections: vec![]
+        });
+
+        record.offers += 1;
+        record.cpus = offer.cpus;
+        record.gpus = offer.gpus;
+        record.mem = offer.mem;
+        record.disk = offer.disk;
+        record.rejections = rejections;
+    }
+
+    pub fn summary(&self, max_candidates: usize) -> String {
+        let mut summary = String::new();
+
+        if self.agents.is_empty() {
+            summary.push_str("No resource offers were received from Mesos.\n");
+            return summary;
+        }
+
+        let mut records: Vec<&AgentRecord> = self.agents.values().collect();
+        records.sort_by(|a, b| a.hostname.cmp(&b.hostname));
+
+        summary.push_str(format!("Received {} offers from {} agents:\n", self.total_offers, records.len()).as_str());
+
+        for record in &records {
+            summary.push_str(format!(
+                "  {} ({} offers, cpus: {}, mem: {}, disk: {}, gpus: {})\n",
+                record.hostname, record.offers, record.cpus, record.mem, record.disk, record.gpus
+            ).as_str());
+
+            if record.rejections.is_empty() {
+                summary.push_str("    acceptable\n");
+            }
+
+            for rejection in &record.rejections {
+                summary.push_str(format!("    {}\n", rejection.reason).as_str());
+            }
+
+        }
+
+        let mut candidates: Vec<&AgentRecord> = records.iter()
+            .filter(|record| !record.rejections.is_empty())
+            .map(|record| *record)
+            .collect();
+
+        candidates.sort_by(|a, b| a.score().partial_cmp(&b.score()).unwrap_or(::std::cmp::Ordering::Equal));
+
+        if !candidates.is_empty() {
+            summary.push_str("Closest candidates:\n");
+
+            for (index, record) in candidates.iter().take(max_candidates).enumerate() {
+                let reasons: Vec<&str> = record.rejections.iter().map(|rejection| rejection.reason.as_str()).collect();
+
+                summary.push_str(format!("  {}. {} - {}\n", index + 1, record.hostname, reasons.join("; ")).as_str());
+            }
+
+        }
+
+        summary
+    }
+
+}
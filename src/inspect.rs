@@ -0,0 +1,371 @@
+use constraints::{ConstraintContext, Constraints};
+use mesos;
+use network;
+use reqwest;
+use scheduler;
+use serde_json;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::process::exit;
+use types;
+
+/**
+ * The agents, tasks and frameworks commands.  These read the cluster through the master's operator API to
+ * help choose flags such as -a and -c.
+ **/
+
+const SCALARS: &'static [&'static str] = &["cpus", "mem", "disk", "gpus"];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+    Table,
+    Json
+}
+
+fn query(mesos_host: &str, http: &types::HttpConfig, discovery: &types::DiscoveryConfig, call: &str) -> (String, Value) {
+    let master_url = mesos::create_leader_discovery(mesos_host, http, discovery).discover();
+    let client: reqwest::Client = network::create_json_client(http);
+
+    match mesos::operator_query(&client, master_url.as_str(), &json!({ "type": call })) {
+        Ok(response) => (master_url, response),
+        Err(e) => {
+            error!("Unable to {} from {}: {}", call, master_url, e);
+            exit(1);
+        }
+    }
+
+}
+
+/**
+ * Sums the scalar resources by name.
+ **/
+fn scalar_resources(resources: &Value) -> HashMap<String, f64> {
+    let mut totals: HashMap<String, f64> = HashMap::new();
+
+    if let Some(resources) = resources.as_array() {
+
+        for resource in resources {
+
+            if let (Some(name), Some(value)) = (resource["name"].as_str(), resource["scalar"]["value"].as_f64()) {
+                *totals.entry(String::from(name)).or_insert(0.0) += value;
+            }
+
+        }
+
+    }
+
+    totals
+}
+
+fn scalar(resources: &HashMap<String, f64>, name: &str) -> f64 {
+    *resources.get(name).unwrap_or(&0.0)
+}
+
+fn scalars_json(resources: &HashMap<String, f64>) -> Value {
+    let mut map = Map::new();
+
+    for name in SCALARS {
+        map.insert(String::from(*name), Value::from(scalar(resources, name)));
+    }
+
+    Value::Object(map)
+}
+
+fn format_number(value: f64) -> String {
+
+    match value.fract() == 0.0 {
+        true => format!("{}", value as i64),
+        false => format!("{:.2}", value)
+    }
+
+}
+
+/**
+ * Left aligned columns separated by two spaces, sized to their widest value.
+ **/
+fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+
+    for row in rows {
+
+        for (index, cell) in row.iter().enumerate() {
+
+            if cell.len() > widths[index] {
+                widths[index] = cell.len();
+            }
+
+        }
+
+    }
+
+    let format_row = |cells: Vec<&str>| -> String {
+        let padded: Vec<String> = cells.iter().enumerate().map(|(index, cell)| format!("{:width$}", cell, width = widths[index])).collect();
+        String::from(padded.join("  ").trim_right())
+    };
+
+    println!("{}", format_row(headers.to_vec()));
+
+    for row in rows {
+        println!("{}", format_row(row.iter().map(|cell| cell.as_str()).collect()));
+    }
+
+}
+
+fn print_json(value: &Value) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
+
+/**
+ * Lists agents matching the constraints with their attributes and free, used and total resources.
+ **/
+pub fn agents(mesos_host: &str, constraints: &Constraints, format: Format, http: &types::HttpConfig, discovery: &types::DiscoveryConfig) {
+    let (master_url, response) = query(mesos_host, http, discovery, "GET_AGENTS");
+
+    let context = match constraints.needs_context() {
+        true => match ConstraintContext::fetch(master_url.as_str(), http, scheduler::FRAMEWORK_NAME) {
+            Ok(context) => context,
+            Err(e) => {
+                error!("Unable to evaluate GROUP_BY/MAX_PER constraints: {}", e);
+                exit(1);
+            }
+        },
+        false => ConstraintContext::empty()
+    };
+
+    let mut rows: Vec<Vec<String>> = vec![];
+    let mut agents_json: Vec<Value> = vec![];
+
+    if let Some(agents) = response["get_agents"]["agents"].as_array() {
+
+        for agent in agents {
+            let agent_info = &agent["agent_info"];
+            let hostname = agent_info["hostname"].as_str().unwrap_or("");
+            let attributes = types::parse_attributes(&agent_info["attributes"], hostname);
+
+            if constraints.check(&attributes, &context).is_err() {
+                continue;
+            }
+
+            let total = scalar_resources(&agent["total_resources"]);
+            let used = scalar_resources(&agent["allocated_resources"]);
+            let free: HashMap<String, f64> = SCALARS.iter()
+                .map(|name| (String::from(*name), scalar(&total, name) - scalar(&used, name)))
+                .collect();
+
+            let mut attribute_names: Vec<&String> = attributes.keys().filter(|name| name.as_str() != "hostname").collect();
+            attribute_names.sort();
+
+            let attribute_text: Vec<String> = attribute_names.iter()
+                .map(|name| format!("{}={}", name, attributes[*name]))
+                .collect();
+
+            rows.push(vec![
+                String::from(hostname),
+                String::from(agent_info["id"]["value"].as_str().unwrap_or("")),
+                format!("{}/{}", format_number(scalar(&free, "cpus")), format_number(scalar(&total, "cpus"))),
+                format!("{}/{}", format_number(scalar(&free, "mem")), format_number(scalar(&total, "mem"))),
+                format!("{}/{}", format_number(scalar(&free, "disk")), format_number(scalar(&total, "disk"))),
+                format!("{}/{}", format_number(scalar(&free, "gpus")), format_number(scalar(&total, "gpus"))),
+                attribute_text.join(",")
+            ]);
+
+            let mut attributes_json = Map::new();
+
+            for name in attribute_names {
+                attributes_json.insert(name.clone(), Value::from(attributes[name].to_string()));
+            }
+
+            agents_json.push(json!({
+                "id": agent_info["id"]["value"],
+                "hostname": hostname,
+                "port": agent_info["port"],
+                "active": agent["active"],
+                "attributes": attributes_json,
+                "resources": {
+                    "free": scalars_json(&free),
+                    "used": scalars_json(&used),
+                    "total": scalars_json(&total)
+                }
+            }));
+        }
+
+    }
+
+    match format {
+        Format::Table => print_table(&["HOSTNAME", "ID", "CPUS", "MEM", "DISK", "GPUS", "ATTRIBUTES"], &rows[..]),
+        Format::Json => print_json(&Value::Array(agents_json))
+    };
+
+}
+
+fn agent_hostnames(state: &Value) -> HashMap<String, String> {
+    let mut hostnames: HashMap<String, String> = HashMap::new();
+
+    if let Some(agents) = state["get_agents"]["agents"].as_array() {
+
+        for agent in agents {
+
+            if let (Some(id), Some(hostname)) = (agent["agent_info"]["id"]["value"].as_str(), agent["agent_info"]["hostname"].as_str()) {
+                hostnames.insert(String::from(id), String::from(hostname));
+            }
+
+        }
+
+    }
+
+    hostnames
+}
+
+fn framework_names(state: &Value) -> HashMap<String, String> {
+    let mut names: HashMap<String, String> = HashMap::new();
+
+    let lists = ["frameworks", "completed_frameworks"];
+
+    for list in lists.iter() {
+
+        if let Some(frameworks) = state["get_frameworks"][*list].as_array() {
+
+            for framework in frameworks {
+
+                if let (Some(id), Some(name)) = (framework["framework_info"]["id"]["value"].as_str(), framework["framework_info"]["name"].as_str()) {
+                    names.insert(String::from(id), String::from(name));
+                }
+
+            }
+
+        }
+
+    }
+
+    names
+}
+
+/**
+ * Lists the running tasks, or every task the master knows about with all.
+ **/
+pub fn tasks(mesos_host: &str, all: bool, format: Format, http: &types::HttpConfig, discovery: &types::DiscoveryConfig) {
+    let (_, response) = query(mesos_host, http, discovery, "GET_STATE");
+    let state = &response["get_state"];
+    let hostnames = agent_hostnames(state);
+    let frameworks = framework_names(state);
+
+    let lists: Vec<&str> = match all {
+        true => vec!["tasks", "completed_tasks", "unreachable_tasks", "orphan_tasks"],
+        false => vec!["tasks"]
+    };
+
+    let mut rows: Vec<Vec<String>> = vec![];
+    let mut tasks_json: Vec<Value> = vec![];
+
+    for list in lists {
+
+        if let Some(tasks) = state["get_tasks"][list].as_array() {
+
+            for task in tasks {
+                let framework_id = task["framework_id"]["value"].as_str().unwrap_or("");
+                let agent_id = task["agent_id"]["value"].as_str().unwrap_or("");
+                let framework = frameworks.get(framework_id).cloned().unwrap_or_default();
+                let hostname = hostnames.get(agent_id).cloned().unwrap_or_default();
+                let resources = scalar_resources(&task["resources"]);
+
+                rows.push(vec![
+                    String::from(task["task_id"]["value"].as_str().unwrap_or("")),
+                    String::from(task["name"].as_str().unwrap_or("")),
+                    framework.clone(),
+                    String::from(task["state"].as_str().unwrap_or("")),
+                    hostname.clone(),
+                    format_number(scalar(&resources, "cpus")),
+                    format_number(scalar(&resources, "mem"))
+                ]);
+
+                tasks_json.push(json!({
+                    "id": task["task_id"]["value"],
+                    "name": task["name"],
+                    "state": task["state"],
+                    "framework_id": framework_id,
+                    "framework": framework,
+                    "agent_id": agent_id,
+                    "hostname": hostname,
+                    "resources": scalars_json(&resources)
+                }));
+            }
+
+        }
+
+    }
+
+    match format {
+        Format::Table => print_table(&["ID", "NAME", "FRAMEWORK", "STATE", "HOSTNAME", "CPUS", "MEM"], &rows[..]),
+        Format::Json => print_json(&Value::Array(tasks_json))
+    };
+
+}
+
+/**
+ * Lists the registered frameworks with the number of active tasks and the resources they use.
+ **/
+pub fn frameworks(mesos_host: &str, format: Format, http: &types::HttpConfig, discovery: &types::DiscoveryConfig) {
+    let (_, response) = query(mesos_host, http, discovery, "GET_STATE");
+    let state = &response["get_state"];
+
+    // Active task count and resources per framework id.
+    let mut usage: HashMap<String, (u32, HashMap<String, f64>)> = HashMap::new();
+
+    if let Some(tasks) = state["get_tasks"]["tasks"].as_array() {
+
+        for task in tasks {
+            let framework_id = String::from(task["framework_id"]["value"].as_str().unwrap_or(""));
+            let entry = usage.entry(framework_id).or_insert((0, HashMap::new()));
+
+            entry.0 += 1;
+
+            for (name, value) in scalar_resources(&task["resources"]) {
+                *entry.1.entry(name).or_insert(0.0) += value;
+            }
+
+        }
+
+    }
+
+    let mut rows: Vec<Vec<String>> = vec![];
+    let mut frameworks_json: Vec<Value> = vec![];
+
+    if let Some(frameworks) = state["get_frameworks"]["frameworks"].as_array() {
+
+        for framework in frameworks {
+            let framework_info = &framework["framework_info"];
+            let id = framework_info["id"]["value"].as_str().unwrap_or("");
+            let (task_count, resources) = usage.get(id).cloned().unwrap_or((0, HashMap::new()));
+
+            rows.push(vec![
+                String::from(id),
+                String::from(framework_info["name"].as_str().unwrap_or("")),
+                String::from(framework_info["role"].as_str().unwrap_or("*")),
+                match framework["active"].as_bool() {
+                    Some(true) => String::from("yes"),
+                    _ => String::from("no")
+                },
+                task_count.to_string(),
+                format_number(scalar(&resources, "cpus")),
+                format_number(scalar(&resources, "mem"))
+            ]);
+
+            frameworks_json.push(json!({
+                "id": id,
+                "name": framework_info["name"],
+                "role": framework_info["role"],
+                "active": framework["active"],
+                "connected": framework["connected"],
+                "tasks": task_count,
+                "resources": scalars_json(&resources)
+            }));
+        }
+
+    }
+
+    match format {
+        Format::Table => print_table(&["ID", "NAME", "ROLE", "ACTIVE", "TASKS", "CPUS", "MEM"], &rows[..]),
+        Format::Json => print_json(&Value::Array(frameworks_json))
+    };
+
+}
@@ -0,0 +1,349 @@
+use console;
+use console::Console;
+use mesos;
+use network;
+use reqwest;
+use scheduler;
+use serde_json::Value;
+use std::fmt;
+use std::io::{stderr, stdout, Write};
+use std::process::exit;
+use std::thread;
+use std::time::Duration;
+use types;
+
+/**
+ * Follows up on tasks launched with --detach.  The handle printed at launch is framework_id/task_id/agent_id.
+ * Tasks are found through the master's operator API and their output is read from the agent, so none of
+ * these need to re-subscribe as the framework.
+ **/
+
+const POLL_SECONDS: u64 = 1;
+
+pub struct JobHandle {
+    pub framework_id: String,
+    pub task_id: String,
+    pub agent_id: String
+}
+
+struct JobTask {
+    state: String,
+    executor_id: String,
+    container_id: Option<String>
+}
+
+impl JobHandle {
+
+    pub fn parse(value: &str) -> Result<JobHandle, String> {
+        let parts: Vec<&str> = value.trim().split('/').collect();
+
+        if parts.len() != 3 || parts.iter().any(|part| part.is_empty()) {
+            return Err(format!("Invalid job handle {}.  Expected framework_id/task_id/agent_id", value));
+        }
+
+        Ok(JobHandle {
+            framework_id: String::from(parts[0]),
+            task_id: String::from(parts[1]),
+            agent_id: String::from(parts[2])
+        })
+    }
+
+}
+
+impl fmt::Display for JobHandle {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}/{}", self.framework_id, self.task_id, self.agent_id)
+    }
+
+}
+
+pub fn is_terminal(state: &str) -> bool {
+
+    match state {
+        "TASK_FINISHED" | "TASK_FAILED" | "TASK_KILLED" | "TASK_ERROR" | "TASK_LOST" | "TASK_DROPPED" | "TASK_GONE" => true,
+        _ => false
+    }
+
+}
+
+/**
+ * Looks the task up in GET_TASKS.  Tasks of a framework that has been torn down are in completed_tasks.
+ **/
+fn find_task(client: &reqwest::Client, master_url: &str, handle: &JobHandle) -> Result<JobTask, String> {
+
+    let response = match mesos::operator_query(client, master_url, &json!({ "type": "GET_TASKS" })) {
+        Ok(response) => response,
+        Err(e) => return Err(format!("Unable to get tasks from {}: {}", master_url, e))
+    };
+
+    let lists = ["tasks", "completed_tasks", "unreachable_tasks"];
+    let get_tasks = &response["get_tasks"];
+
+    let task = lists.iter()
+        .filter_map(move |list| get_tasks[*list].as_array())
+        .flat_map(|tasks| tasks.iter())
+        .find(|task| {
+            task["task_id"]["value"].as_str() == Some(handle.task_id.as_str()) &&
+                task["framework_id"]["value"].as_str() == Some(handle.framework_id.as_str())
+        });
+
+    let task = match task {
+        Some(task) => task,
+        None => return Err(format!("Task {} isn't known to the master", handle.task_id))
+    };
+
+    let latest_status = match task["statuses"].as_array() {
+        Some(statuses) => statuses.last().cloned().unwrap_or(Value::Null),
+        None => Value::Null
+    };
+
+    Ok(JobTask {
+        state: String::from(task["state"].as_str().unwrap_or("TASK_UNKNOWN")),
+        // The command and docker executors are given the id of the task they run.
+        executor_id: String::from(latest_status["executor_id"]["value"].as_str().unwrap_or(handle.task_id.as_str())),
+        container_id: latest_status["container_status"]["container_id"]["value"].as_str().map(String::from)
+    })
+}
+
+/**
+ * Agents are assumed to use the same scheme as the master.
+ **/
+fn find_agent_url(client: &reqwest::Client, master_url: &str, agent_id: &str) -> Result<String, String> {
+
+    let response = match mesos::operator_query(client, master_url, &json!({ "type": "GET_AGENTS" })) {
+        Ok(response) => response,
+        Err(e) => return Err(format!("Unable to get agents from {}: {}", master_url, e))
+    };
+
+    let agent = match response["get_agents"]["agents"].as_array() {
+        Some(agents) => agents.iter().find(|agent| agent["agent_info"]["id"]["value"].as_str() == Some(agent_id)),
+        None => None
+    };
+
+    let scheme = match master_url.find("://") {
+        Some(index) => &master_url[..index],
+        None => "http"
+    };
+
+    match agent {
+        Some(agent) => Ok(format!(
+            "{}://{}:{}",
+            scheme,
+            agent["agent_info"]["hostname"].as_str().unwrap_or(""),
+            agent["agent_info"]["port"].as_i64().unwrap_or(5051)
+        )),
+        None => Err(format!("Agent {} isn't known to the master", agent_id))
+    }
+
+}
+
+fn discover_master(mesos_host: &str, http: &types::HttpConfig, discovery: &types::DiscoveryConfig) -> String {
+    mesos::create_leader_discovery(mesos_host, http, discovery).discover()
+}
+
+fn find_task_or_exit(client: &reqwest::Client, master_url: &str, handle: &JobHandle) -> JobTask {
+
+    match find_task(client, master_url, handle) {
+        Ok(task) => task,
+        Err(e) => {
+            error!("{}", e);
+            exit(1);
+        }
+    }
+
+}
+
+/**
+ * Polls the master until the task has stopped and returns its final state.
+ **/
+fn wait_for_task(client: &reqwest::Client, master_url: &str, handle: &JobHandle) -> String {
+
+    loop {
+        let task = find_task_or_exit(client, master_url, handle);
+
+        if is_terminal(task.state.as_str()) {
+            return task.state;
+        }
+
+        thread::sleep(Duration::from_secs(POLL_SECONDS));
+    }
+
+}
+
+/**
+ * Removes the framework once its task has stopped.  A detached framework otherwise stays registered until
+ * its failover timeout.
+ **/
+fn teardown(client: &reqwest::Client, master_url: &str, framework_id: &str) -> Result<(), String> {
+
+    let body = json!({
+        "type": "TEARDOWN",
+        "teardown": {
+            "framework_id": { "value": framework_id }
+        }
+    });
+
+    match mesos::operator_call(client, master_url, &body) {
+        Ok(_) => Ok(()),
+        Err(e) => Err(format!("Unable to tear down framework {}: {}", framework_id, e))
+    }
+
+}
+
+fn finish(client: &reqwest::Client, master_url: &str, handle: &JobHandle, state: &str) {
+
+    if let Err(e) = teardown(client, master_url, handle.framework_id.as_str()) {
+        warn!("{}", e);
+    }
+
+    let _ = stdout().flush();
+
+    match state {
+        "TASK_FINISHED" => exit(0),
+        _ => {
+            error!("Task {} ended with {}", handle.task_id, state);
+            exit(1);
+        }
+    };
+
+}
+
+fn open_console(client: &reqwest::Client, master_url: &str, handle: &JobHandle, task: &JobTask, follow_mode: types::FollowMode, show_stderr: bool, chunk_size: u64, http: &types::HttpConfig) -> console::HeadlessConsole {
+
+    let container_id = match task.container_id {
+        Some(ref container_id) => container_id.clone(),
+        None => {
+            error!("Task {} hasn't started a container", handle.task_id);
+            exit(1);
+        }
+    };
+
+    let agent_url = match find_agent_url(client, master_url, handle.agent_id.as_str()) {
+        Ok(agent_url) => agent_url,
+        Err(e) => {
+            error!("{}", e);
+            exit(1);
+        }
+    };
+
+    let mut agent_state_url = agent_url.clone();
+    agent_state_url.push_str("/state");
+
+    let sandbox_path = scheduler::get_sandbox_path(
+        agent_state_url,
+        client,
+        handle.agent_id.as_str(),
+        handle.framework_id.as_str(),
+        task.executor_id.as_str(),
+        container_id.as_str()
+    );
+
+    let mut api_url = agent_url;
+    api_url.push_str("/api/v1");
+
+    let stderr_writer: Option<Box<Write + Send>> = match show_stderr {
+        true => Some(Box::new(stderr())),
+        false => None
+    };
+
+    console::HeadlessConsole::open(
+        api_url.as_str(),
+        sandbox_path.as_str(),
+        container_id.as_str(),
+        &follow_mode,
+        chunk_size,
+        http,
+        Box::new(stdout()),
+        stderr_writer
+    )
+}
+
+fn parse_handle(value: &str) -> JobHandle {
+
+    match JobHandle::parse(value) {
+        Ok(handle) => handle,
+        Err(e) => {
+            error!("{}", e);
+            exit(1);
+        }
+    }
+
+}
+
+/**
+ * Prints the task's output so far and streams the rest until it stops, then exits with its result like a task
+ * run without --detach.
+ **/
+pub fn attach(mesos_host: &str, handle: &str, show_stderr: bool, chunk_size: u64, http: &types::HttpConfig, discovery: &types::DiscoveryConfig) {
+    let handle = parse_handle(handle);
+    let master_url = discover_master(mesos_host, http, discovery);
+    let client = network::create_json_client(http);
+    let task = find_task_or_exit(&client, master_url.as_str(), &handle);
+
+    if is_terminal(task.state.as_str()) {
+
+        // The container has gone so there's nothing to attach to.  Print what it left in the sandbox.
+        if task.container_id.is_some() {
+            let mut console = open_console(&client, master_url.as_str(), &handle, &task, types::FollowMode::Poll, show_stderr, chunk_size, http);
+
+            console.finish();
+        }
+
+        finish(&client, master_url.as_str(), &handle, task.state.as_str());
+    }
+
+    let mut console = open_console(&client, master_url.as_str(), &handle, &task, types::FollowMode::Auto, show_stderr, chunk_size, http);
+    let state = wait_for_task(&client, master_url.as_str(), &handle);
+
+    console.finish();
+    finish(&client, master_url.as_str(), &handle, state.as_str());
+}
+
+/**
+ * Prints the output the task has written to its sandbox so far.  With follow, keeps printing new output until
+ * the task stops.
+ **/
+pub fn logs(mesos_host: &str, handle: &str, follow: bool, show_stderr: bool, chunk_size: u64, http: &types::HttpConfig, discovery: &types::DiscoveryConfig) {
+    let handle = parse_handle(handle);
+    let master_url = discover_master(mesos_host, http, discovery);
+    let client = network::create_json_client(http);
+    let task = find_task_or_exit(&client, master_url.as_str(), &handle);
+
+    let mut console = open_console(&client, master_url.as_str(), &handle, &task, types::FollowMode::Poll, show_stderr, chunk_size, http);
+
+    if follow && !is_terminal(task.state.as_str()) {
+        wait_for_task(&client, master_url.as_str(), &handle);
+    }
+
+    console.finish();
+
+    let _ = stdout().flush();
+}
+
+pub fn wait(mesos_host: &str, handle: &str, http: &types::HttpConfig, discovery: &types::DiscoveryConfig) {
+    let handle = parse_handle(handle);
+    let master_url = discover_master(mesos_host, http, discovery);
+    let client = network::create_json_client(http);
+    let state = wait_for_task(&client, master_url.as_str(), &handle);
+
+    println!("{}", state);
+
+    finish(&client, master_url.as_str(), &handle, state.as_str());
+}
+
+/**
+ * Each detached task has its own framework, so tearing the framework down kills the task.
+ **/
+pub fn kill(mesos_host: &str, handle: &str, http: &types::HttpConfig, discovery: &types::DiscoveryConfig) {
+    let handle = parse_handle(handle);
+    let master_url = discover_master(mesos_host, http, discovery);
+    let client = network::create_json_client(http);
+
+    if let Err(e) = teardown(&client, master_url.as_str(), handle.framework_id.as_str()) {
+        error!("{}", e);
+        exit(1);
+    }
+
+    println!("Killed task {}", handle.task_id);
+}
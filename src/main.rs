@@ -2,6 +2,7 @@ extern crate base64;
 extern crate clap;
 extern crate ctrlc;
 extern crate env_logger;
+extern crate glob;
 #[macro_use]
 extern crate hyper;
 #[macro_use]
@@ -12,24 +13,307 @@ extern crate reqwest;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[macro_use]
 extern crate serde_json;
+extern crate sha2;
 extern crate terminal_size;
 extern crate timer;
+extern crate tiny_http;
 extern crate chrono;
 extern crate strum;
 #[macro_use]
 extern crate strum_macros; // 0.10.0
 
-use clap::{App, Arg, ArgMatches};
+use clap::{App, AppSettings, Arg, ArgMatches, ErrorKind, SubCommand};
+use glob::Pattern;
 use regex::Regex;
 use std::collections::HashMap;
+use std::env;
+use std::ffi::OsString;
+use std::time::Duration;
+use constraints::Constraints;
+use volumes::VolumeSpec;
 use types::RequestedTaskInfo;
 
 mod scheduler;
+mod artifacts;
+mod constraints;
+mod explain;
+mod selector;
 mod console;
 mod types;
+mod volumes;
 mod network;
 mod mesos;
+mod output;
+mod events;
+mod upload;
+mod jobs;
+mod inspect;
+
+fn parse_constraints(matches: &ArgMatches, name: &str) -> Constraints {
+    let mut constraints = Constraints::new();
+
+    if matches.is_present(name) {
+        let constraint_list: Vec<_> = matches.values_of(name).unwrap().collect();
+
+        for arg in constraint_list {
+
+            match constraints::parse(arg) {
+                Ok(group) => constraints.add(group),
+                Err(e) => {
+                    error!("Invalid attribute constraint: {}", e);
+                    std::process::exit(1);
+                }
+            };
+
+        }
+
+    }
+
+    constraints
+}
+
+/**
+ * Parses a size in bytes with an optional K, M or G suffix.  Eg. 512K or 10M.
+ **/
+fn parse_size(value: &str) -> Option<u64> {
+    let value = value.trim().to_uppercase();
+
+    let (number, multiplier) = match value.chars().last() {
+        Some('K') => (&value[..value.len() - 1], 1024),
+        Some('M') => (&value[..value.len() - 1], 1024 * 1024),
+        Some('G') => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value.as_str(), 1)
+    };
+
+    match number.parse::<u64>() {
+        Ok(size) if size > 0 => Some(size * multiplier),
+        _ => None
+    }
+
+}
+
+/**
+ * Parses local:remote.  The remote path is relative to the sandbox and defaults to the local file's name.
+ **/
+fn parse_upload(value: &str) -> Result<(String, String), String> {
+
+    let (local, remote) = match value.rfind(':') {
+        Some(index) => (&value[..index], String::from(&value[index + 1..])),
+        None => (value, match std::path::Path::new(value).file_name() {
+            Some(name) => name.to_string_lossy().into_owned(),
+            None => return Err(format!("{} is not a file", value))
+        })
+    };
+
+    if local.is_empty() || remote.is_empty() {
+        return Err(format!("Invalid upload {}.  Expected local:remote", value));
+    }
+
+    if remote.starts_with('/') || remote.split('/').any(|part| part == "..") {
+        return Err(format!("Upload destination {} must be relative to the sandbox", remote));
+    }
+
+    Ok((String::from(local), remote))
+}
+
+/**
+ * Parses host:container[/protocol].  The protocol defaults to tcp.
+ **/
+fn parse_port_mapping(value: &str) -> Result<(u64, u64, String), String> {
+
+    let (ports, protocol) = match value.find('/') {
+        Some(index) => (&value[..index], value[index + 1..].to_lowercase()),
+        None => (value, String::from("tcp"))
+    };
+
+    if protocol != "tcp" && protocol != "udp" {
+        return Err(format!("Invalid protocol {} in port mapping {}.  Expected tcp or udp", protocol, value));
+    }
+
+    let parts: Vec<&str> = ports.split(':').collect();
+
+    if parts.len() != 2 {
+        return Err(format!("Invalid port mapping {}.  Expected host:container[/protocol]", value));
+    }
+
+    match (parts[0].parse::<u64>(), parts[1].parse::<u64>()) {
+        (Ok(host_port), Ok(container_port)) if host_port > 0 && host_port <= 65535 && container_port > 0 && container_port <= 65535 => {
+            Ok((host_port, container_port, protocol))
+        },
+        _ => Err(format!("Invalid port numbers in port mapping {}", value))
+    }
+
+}
+
+const RLIMITS: &'static [&'static str] = &[
+    "as", "core", "cpu", "data", "fsize", "locks", "memlock", "msgqueue", "nice", "nofile", "nproc", "rss",
+    "rtprio", "rttime", "sigpending", "stack"
+];
+
+/**
+ * Parses name=soft[:hard].  A limit of "unlimited" applies to both soft and hard.
+ **/
+fn parse_rlimit(value: &str) -> Result<(String, Option<u64>, Option<u64>), String> {
+
+    let parts: Vec<&str> = value.splitn(2, '=').collect();
+
+    if parts.len() != 2 {
+        return Err(format!("Invalid rlimit {}.  Expected name=soft[:hard]", value));
+    }
+
+    let name = parts[0].trim().to_lowercase();
+
+    if !RLIMITS.contains(&name.as_str()) {
+        return Err(format!("Unknown rlimit {}.  Expected one of {}", name, RLIMITS.join(", ")));
+    }
+
+    let parse_limit = |limit: &str| -> Result<Option<u64>, String> {
+
+        match limit {
+            "unlimited" => Ok(None),
+            _ => match limit.parse::<u64>() {
+                Ok(limit) => Ok(Some(limit)),
+                Err(_) => Err(format!("Invalid limit {} in rlimit {}", limit, value))
+            }
+        }
+
+    };
+
+    let limits: Vec<&str> = parts[1].split(':').collect();
+
+    let (soft, hard) = match limits.len() {
+        1 => {
+            let limit = parse_limit(limits[0])?;
+            (limit, limit)
+        },
+        2 => (parse_limit(limits[0])?, parse_limit(limits[1])?),
+        _ => return Err(format!("Invalid rlimit {}.  Expected name=soft[:hard]", value))
+    };
+
+    match (soft, hard) {
+        (Some(soft), Some(hard)) if soft > hard => Err(format!("Soft limit is above the hard limit in rlimit {}", value)),
+        // Mesos requires both limits to be set or both to be unset.
+        (None, Some(_)) |
+        (Some(_), None) => Err(format!("Soft and hard limits must both be unlimited or both be numbers in rlimit {}", value)),
+        _ => Ok((name, soft, hard))
+    }
+
+}
+
+/**
+ * Parses id:size:container_path.  The size is in MB and the container path is relative to the sandbox.
+ **/
+fn parse_persistent_volume(value: &str) -> Result<types::PersistentVolumeConfig, String> {
+    let parts: Vec<&str> = value.splitn(3, ':').collect();
+
+    if parts.len() != 3 || parts.iter().any(|part| part.is_empty()) {
+        return Err(format!("Invalid persistent volume {}.  Expected id:size:container_path", value));
+    }
+
+    let size = match parts[1].parse::<f32>() {
+        Ok(size) if size > 0.0 => size,
+        _ => return Err(format!("Invalid size {} in persistent volume {}.  Expected MB greater than 0", parts[1], value))
+    };
+
+    if parts[2].starts_with('/') || parts[2].split('/').any(|part| part == "..") {
+        return Err(format!("Persistent volume path {} must be relative to the sandbox", parts[2]));
+    }
+
+    Ok(types::PersistentVolumeConfig {
+        id: String::from(parts[0]),
+        size,
+        container_path: String::from(parts[2])
+    })
+}
+
+/**
+ * Parses http:port[/path], https:port[/path], tcp:port or cmd:command.
+ **/
+fn parse_health_check(value: &str) -> Result<types::HealthCheckTarget, String> {
+    let parts: Vec<&str> = value.splitn(2, ':').collect();
+
+    if parts.len() != 2 || parts[1].is_empty() {
+        return Err(format!("Invalid health check {}.  Expected http:port[/path], https:port[/path], tcp:port or cmd:command", value));
+    }
+
+    let parse_port = |port: &str| -> Result<u32, String> {
+
+        match port.parse::<u32>() {
+            Ok(port) if port > 0 && port <= 65535 => Ok(port),
+            _ => Err(format!("Invalid port {} in health check {}", port, value))
+        }
+
+    };
+
+    match parts[0].to_lowercase().as_str() {
+        scheme @ "http" | scheme @ "https" => {
+
+            let (port, path) = match parts[1].find('/') {
+                Some(index) => (&parts[1][..index], &parts[1][index..]),
+                None => (parts[1], "/")
+            };
+
+            Ok(types::HealthCheckTarget::Http(String::from(scheme), parse_port(port)?, String::from(path)))
+        },
+        "tcp" => Ok(types::HealthCheckTarget::Tcp(parse_port(parts[1])?)),
+        "cmd" => Ok(types::HealthCheckTarget::Command(String::from(parts[1]))),
+        check_type => Err(format!("Unknown health check type {}.  Expected http, https, tcp or cmd", check_type))
+    }
+
+}
+
+/**
+ * Settings for talking to the masters and agents, shared by running a task and the subcommands.
+ **/
+fn parse_connection(matches: &ArgMatches) -> (types::HttpConfig, types::DiscoveryConfig) {
+
+    let master_scheme = match matches.value_of("master_scheme") {
+        Some("http") => types::MasterScheme::Http,
+        Some("https") => types::MasterScheme::Https,
+        _ => types::MasterScheme::Auto
+    };
+
+    let zk_timeout: u64 = match matches.value_of("zk_timeout").unwrap().parse::<u64>() {
+        Ok(value) if value > 0 => value,
+        _ => {
+            error!("Zookeeper timeout must be a number of seconds greater than 0");
+            std::process::exit(1);
+        }
+    };
+
+    let http_timeout: u64 = match matches.value_of("http_timeout").unwrap().parse::<u64>() {
+        Ok(value) => value,
+        Err(_) => {
+            error!("HTTP timeout specified is not a valid number of seconds");
+            std::process::exit(1);
+        }
+    };
+
+    let tls = types::TlsConfig {
+        ca_cert: matches.value_of("ca_cert").map(String::from),
+        client_cert: matches.value_of("client_cert").map(String::from),
+        client_cert_password: String::from(matches.value_of("client_cert_password").unwrap_or("")),
+        insecure_skip_hostname_verify: matches.occurrences_of("insecure_skip_hostname_verify") > 0
+    };
+
+    let http = types::HttpConfig {
+        tls,
+        timeout: match http_timeout {
+            0 => None,
+            seconds => Some(Duration::from_secs(seconds))
+        }
+    };
+
+    let discovery = types::DiscoveryConfig {
+        master_scheme,
+        zk_timeout: Duration::from_secs(zk_timeout),
+        follow_leader: matches.occurrences_of("follow_leader") > 0
+    };
+
+    (http, discovery)
+}
 
 fn generate_task_info<'a>(ref matches: &'a ArgMatches) -> RequestedTaskInfo {
     let executor: String = String::from(matches.value_of("executor").unwrap());
@@ -148,34 +432,47 @@ fn generate_task_info<'a>(ref matches: &'a ArgMatches) -> RequestedTaskInfo {
 
     }
 
-    let mut volumes: Vec<(String, String, Option<String>)> = vec![];
+    let mut volumes: Vec<VolumeSpec> = vec![];
 
     if matches.is_present("volume") {
-        let volume_defs: Vec<_> = matches.values_of("volume").unwrap().collect();
 
-        for def in volume_defs {
-            let parts: Vec<&str> = def.split(":").collect();
+        for def in matches.values_of("volume").unwrap() {
 
-            match parts.len() {
-                2 => volumes.push((String::from(parts[0]), String::from(parts[1]), None)),
-                3 => {
-                    let mode = parts[2].to_uppercase();
+            match volumes::parse_volume(def) {
+                Ok(volume) => volumes.push(volume),
+                Err(e) => {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            };
 
-                    if mode == "RO" || mode == "RW" {
-                        volumes.push((String::from(parts[0]), String::from(parts[1]), Some(String::from(mode))));
+        }
 
-                    } else {
-                        error!("{} is not a valid VolumeMode", mode);
-                        std::process::exit(1);
-                    }
-                },
-                _ => {
-                    error!("Too many volume parameters");
+    }
+
+    if matches.is_present("mount") {
+
+        for def in matches.values_of("mount").unwrap() {
+
+            match volumes::parse_mount(def) {
+                Ok(volume) => volumes.push(volume),
+                Err(e) => {
+                    error!("{}", e);
                     std::process::exit(1);
                 }
             };
 
         }
+
+    }
+
+    for volume in &volumes {
+
+        if let Err(e) = volumes::validate(volume, executor.as_str()) {
+            error!("{}", e);
+            std::process::exit(1);
+        }
+
     }
 
     let tty = matches.occurrences_of("tty") > 0;
@@ -188,33 +485,340 @@ fn generate_task_info<'a>(ref matches: &'a ArgMatches) -> RequestedTaskInfo {
         tty_mode = types::TTYMode::Headless;
     }
 
-    let mut attrs: HashMap<String, String> = HashMap::new();
+    let constraints = parse_constraints(matches, "attr");
+
+    let offer_strategy = selector::Strategy::from(matches.value_of("strategy").unwrap()).unwrap();
+
+    let offer_window: u64 = match matches.value_of("offer_window") {
+        Some(value) => match value.parse::<u64>() {
+            Ok(parsed_value) => parsed_value,
+            Err(_) => {
+                error!("Offer window specified is not a valid number of milliseconds");
+                std::process::exit(1);
+            }
+        },
+        // First-fit keeps the old behaviour of taking the first usable offer straight away.
+        None => match offer_strategy {
+            selector::Strategy::FirstFit => 0,
+            _ => 2000
+        }
+    };
 
-    if matches.is_present("attr") {
-        let attrs_list: Vec<_> = matches.values_of("attr").unwrap().collect();
+    let prefer = parse_constraints(matches, "prefer");
+    let avoid = parse_constraints(matches, "avoid");
 
-        for arg in attrs_list {
+    let mut collect: Vec<Pattern> = vec![];
 
-            if attr_regex.is_match(arg) {
-                let groups = attr_regex.captures(arg).unwrap();
+    if matches.is_present("collect") {
 
-                attrs.insert(
-                    String::from(groups.get(1).unwrap().as_str()),
-                    String::from(groups.get(2).unwrap().as_str())
-                );
+        for expr in matches.values_of("collect").unwrap() {
 
-            }
+            match Pattern::new(expr) {
+                Ok(pattern) => collect.push(pattern),
+                Err(e) => {
+                    error!("Invalid --collect pattern {}: {}", expr, e);
+                    std::process::exit(1);
+                }
+            };
 
         }
 
     }
 
+    let max_launch_attempts: u32 = match matches.value_of("max_launch_attempts").unwrap().parse::<u32>() {
+        Ok(value) if value > 0 => value,
+        _ => {
+            error!("Maximum launch attempts must be a number greater than 0");
+            std::process::exit(1);
+        }
+    };
+
     let mut timeout: i64 = match matches.value_of("timeout") {
         Some(value) => value.parse::<i64>().unwrap(),
         None => 60
     };
 
+    let (http, discovery) = parse_connection(matches);
+
+    let mut uploads: Vec<(String, String)> = vec![];
+
+    if matches.is_present("upload") {
+
+        for value in matches.values_of("upload").unwrap() {
+
+            match parse_upload(value) {
+                Ok(upload) => uploads.push(upload),
+                Err(e) => {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+
+        }
+
+    }
+
+    let upload_port: u16 = match matches.value_of("upload_port").unwrap().parse::<u16>() {
+        Ok(value) => value,
+        Err(_) => {
+            error!("Upload port specified is not a valid port number");
+            std::process::exit(1);
+        }
+    };
+
+    let chunk_size = parse_chunk_size(matches);
+
+    let event_fd: Option<i32> = match matches.value_of("event_fd") {
+        Some(value) => match value.parse::<i32>() {
+            Ok(fd) if fd >= 0 => Some(fd),
+            _ => {
+                error!("Event file descriptor must be a number of 0 or more");
+                std::process::exit(1);
+            }
+        },
+        None => None
+    };
+
+    let follow_mode = match matches.value_of("follow_mode") {
+        Some("poll") => types::FollowMode::Poll,
+        Some("attach") => types::FollowMode::Attach,
+        _ => types::FollowMode::Auto
+    };
+
+    let mut prefix: Vec<types::PrefixField> = vec![];
+
+    if let Some(value) = matches.value_of("prefix") {
+
+        for field in value.split(',') {
+
+            prefix.push(match field.trim() {
+                "time" => types::PrefixField::Time,
+                "name" => types::PrefixField::Name,
+                "task" => types::PrefixField::Task,
+                "host" => types::PrefixField::Host,
+                "stream" => types::PrefixField::Stream,
+                _ => {
+                    error!("Unknown prefix field {}.  Expected time, name, task, host or stream", field);
+                    std::process::exit(1);
+                }
+            });
+
+        }
+
+    }
+
+    if matches.occurrences_of("timestamps") > 0 && !prefix.contains(&types::PrefixField::Time) {
+        prefix.insert(0, types::PrefixField::Time);
+    }
+
+    let network_mode = match matches.value_of("network") {
+        Some("host") => Some(types::NetworkMode::Host),
+        Some("bridge") => Some(types::NetworkMode::Bridge),
+        Some("none") => Some(types::NetworkMode::None),
+        Some(name) => Some(types::NetworkMode::Named(String::from(name))),
+        None => None
+    };
+
+    if executor == "exec" {
+
+        match network_mode {
+            Some(types::NetworkMode::Bridge) | Some(types::NetworkMode::None) => {
+                error!("The exec executor can only use the host network or a named CNI network");
+                std::process::exit(1);
+            },
+            _ => {}
+        };
+
+    }
+
+    let mut network_labels: Vec<(String, String)> = vec![];
+
+    if matches.is_present("network_label") {
+
+        for label in matches.values_of("network_label").unwrap() {
+
+            match attr_regex.captures(label) {
+                Some(groups) => network_labels.push((
+                    String::from(groups.get(1).unwrap().as_str()),
+                    String::from(groups.get(2).unwrap().as_str())
+                )),
+                None => {
+                    error!("Invalid network label {}.  Expected key=value", label);
+                    std::process::exit(1);
+                }
+            };
+
+        }
+
+    }
+
+    let mut port_mappings: Vec<(u64, u64, String)> = vec![];
+
+    if matches.is_present("publish") {
+
+        for value in matches.values_of("publish").unwrap() {
+
+            match parse_port_mapping(value) {
+                Ok(mapping) => port_mappings.push(mapping),
+                Err(e) => {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+
+        }
+
+    }
+
+    if !port_mappings.is_empty() {
+
+        match network_mode {
+            Some(types::NetworkMode::Host) | Some(types::NetworkMode::None) => {
+                error!("Port mappings need a bridge or named network");
+                std::process::exit(1);
+            },
+            None if executor == "exec" => {
+                error!("Port mappings need a named CNI network with the exec executor");
+                std::process::exit(1);
+            },
+            _ => {}
+        };
+
+    }
+
+    let mut docker_params: Vec<(String, String)> = vec![];
+
+    if matches.is_present("docker_param") {
+
+        for param in matches.values_of("docker_param").unwrap() {
+
+            match attr_regex.captures(param) {
+                Some(groups) => docker_params.push((
+                    String::from(groups.get(1).unwrap().as_str().trim_left_matches('-')),
+                    String::from(groups.get(2).unwrap().as_str())
+                )),
+                None => {
+                    error!("Invalid docker parameter {}.  Expected key=value", param);
+                    std::process::exit(1);
+                }
+            };
+
+        }
+
+    }
+
+    let capabilities: Vec<String> = match matches.values_of("cap_add") {
+        Some(values) => values.map(|value| value.to_uppercase().trim_left_matches("CAP_").to_string()).collect(),
+        None => vec![]
+    };
+
+    let mut rlimits: Vec<(String, Option<u64>, Option<u64>)> = vec![];
+
+    if matches.is_present("rlimit") {
+
+        for value in matches.values_of("rlimit").unwrap() {
+
+            match parse_rlimit(value) {
+                Ok(rlimit) => rlimits.push(rlimit),
+                Err(e) => {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+
+        }
+
+    }
+
+    let privileged = matches.occurrences_of("privileged") > 0;
+
+    if (privileged || !docker_params.is_empty() || !capabilities.is_empty()) && matches.occurrences_of("allow_unsafe") == 0 {
+        error!("--privileged, --docker-param and --cap-add weaken the container's isolation.  Pass --allow-unsafe to use them");
+        std::process::exit(1);
+    }
+
+    if executor != "docker" && (privileged || !docker_params.is_empty()) {
+        error!("--privileged and --docker-param are only supported by the docker executor");
+        std::process::exit(1);
+    }
+
+    let max_file_size: Option<u64> = match matches.value_of("max_file_size") {
+        Some(value) => match parse_size(value) {
+            Some(size) => Some(size),
+            None => {
+                error!("Maximum file size must be a number of bytes greater than 0, optionally followed by K, M or G");
+                std::process::exit(1);
+            }
+        },
+        None => None
+    };
+
+    let max_files: u32 = match matches.value_of("max_files").unwrap().parse::<u32>() {
+        Ok(value) => value,
+        Err(_) => {
+            error!("Number of rotated files to keep is not a valid number");
+            std::process::exit(1);
+        }
+    };
+
+    let health_check = match matches.value_of("health_check") {
+        Some(value) => {
+
+            let target = match parse_health_check(value) {
+                Ok(target) => target,
+                Err(e) => {
+                    error!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            let seconds = |name: &str| -> f64 {
+
+                match matches.value_of(name).unwrap().parse::<f64>() {
+                    Ok(value) if value >= 0.0 => value,
+                    _ => {
+                        error!("--{} must be a number of seconds", name.replace('_', "-"));
+                        std::process::exit(1);
+                    }
+                }
+
+            };
+
+            Some(types::HealthCheckConfig {
+                target,
+                interval: seconds("health_interval"),
+                timeout: seconds("health_timeout"),
+                grace_period: seconds("health_grace_period"),
+                consecutive_failures: match matches.value_of("health_retries").unwrap().parse::<u32>() {
+                    Ok(value) if value > 0 => value,
+                    _ => {
+                        error!("--health-retries must be a number greater than 0");
+                        std::process::exit(1);
+                    }
+                },
+                on_unhealthy: match matches.value_of("on_unhealthy") {
+                    Some("kill") => types::UnhealthyAction::Kill,
+                    Some("fail") => types::UnhealthyAction::Fail,
+                    _ => types::UnhealthyAction::Report
+                }
+            })
+        },
+        None => None
+    };
+
+    let persistent_volume = match matches.value_of("persistent_volume") {
+        Some(value) => match parse_persistent_volume(value) {
+            Ok(volume) => Some(volume),
+            Err(e) => {
+                error!("{}", e);
+                std::process::exit(1);
+            }
+        },
+        None => None
+    };
+
     RequestedTaskInfo {
+        task_name: String::from(matches.value_of("task_name").unwrap()),
         executor,
         image_name: match matches.value_of("IMAGE") {
             Some(image_name) => Some(String::from(image_name)),
@@ -229,27 +833,233 @@ fn generate_task_info<'a>(ref matches: &'a ArgMatches) -> RequestedTaskInfo {
         verbose_output,
         tty,
         tty_mode,
-        attrs,
+        constraints,
         volumes,
         force_pull: matches.occurrences_of("force_pull") > 0,
         stderr,
         shell,
-        timeout
+        timeout,
+        explain: matches.occurrences_of("explain") > 0,
+        max_launch_attempts,
+        offer_strategy,
+        offer_window,
+        prefer,
+        avoid,
+        http,
+        discovery,
+        role: matches.value_of("role").map(String::from),
+        principal: matches.value_of("principal").map(String::from),
+        persistent_volume,
+        health_check,
+        detach: matches.occurrences_of("detach") > 0,
+        dry_run: matches.occurrences_of("dry_run") > 0 || matches.occurrences_of("offline") > 0,
+        collect,
+        collect_dir: String::from(matches.value_of("collect_dir").unwrap()),
+        container_options: types::ContainerOptions {
+            docker_params,
+            privileged,
+            capabilities,
+            rlimits
+        },
+        network: types::NetworkConfig {
+            mode: network_mode,
+            labels: network_labels,
+            port_mappings
+        },
+        upload: types::UploadConfig {
+            files: uploads,
+            host: matches.value_of("upload_host").map(String::from),
+            port: upload_port
+        },
+        follow_mode,
+        chunk_size,
+        events: types::EventConfig {
+            format: match matches.value_of("output_format") {
+                Some("json") => types::OutputFormat::Json,
+                _ => types::OutputFormat::Text
+            },
+            fd: event_fd,
+            file: matches.value_of("event_file").map(String::from),
+            include_output: matches.occurrences_of("event_output") > 0
+        },
+        output: types::OutputConfig {
+            stdout_file: matches.value_of("stdout_file").map(String::from),
+            stderr_file: matches.value_of("stderr_file").map(String::from),
+            log_file: matches.value_of("log_file").map(String::from),
+            max_size: max_file_size,
+            max_files,
+            prefix
+        }
+    }
+}
+
+fn destroy_volume(matches: &ArgMatches) {
+    let (http, discovery) = parse_connection(matches);
+
+    let role = match matches.value_of("role") {
+        Some(role) => String::from(role),
+        None => {
+            error!("--role is needed to find the volume's reservation");
+            std::process::exit(1);
+        }
+    };
+
+    let timeout: u64 = match matches.value_of("timeout").unwrap().parse::<u64>() {
+        Ok(value) if value > 0 => value,
+        _ => {
+            error!("Timeout must be a number of seconds greater than 0");
+            std::process::exit(1);
+        }
+    };
+
+    scheduler::destroy_volume(
+        matches.value_of("mesos").unwrap(),
+        matches.value_of("PERSISTENCE_ID").unwrap(),
+        role.as_str(),
+        matches.value_of("principal"),
+        &http,
+        &discovery,
+        Duration::from_secs(timeout),
+        matches.occurrences_of("verbose") > 0
+    );
+}
+
+fn chunk_size_arg<'a, 'b>() -> Arg<'a, 'b> {
+
+    Arg::with_name("chunk_size")
+        .long("chunk-size")
+        .value_name("SIZE")
+        .required(false)
+        .default_value("100K")
+        .help("Amount of output read from the sandbox per request.  Accepts K, M and G suffixes")
+        .takes_value(true)
+
+}
+
+fn parse_chunk_size(matches: &ArgMatches) -> u64 {
+
+    match parse_size(matches.value_of("chunk_size").unwrap()) {
+        Some(size) => size,
+        None => {
+            error!("Chunk size must be a number of bytes greater than 0, optionally followed by K, M or G");
+            std::process::exit(1);
+        }
+    }
+
+}
+
+fn job_subcommand<'a, 'b>(name: &str, about: &'b str) -> App<'a, 'b> {
+
+    SubCommand::with_name(name)
+        .about(about)
+        .arg(Arg::with_name("mesos")
+            .required(true)
+            .help("Mesos master/zookeeper URL")
+            .value_name("MESOS_MASTER")
+            .index(1)
+        )
+        .arg(Arg::with_name("HANDLE")
+            .required(true)
+            .help("Handle printed by --detach, framework_id/task_id/agent_id")
+            .index(2)
+        )
+
+}
+
+fn inspect_subcommand<'a, 'b>(name: &str, about: &'b str) -> App<'a, 'b> {
+
+    SubCommand::with_name(name)
+        .about(about)
+        .arg(Arg::with_name("mesos")
+            .required(true)
+            .help("Mesos master/zookeeper URL")
+            .value_name("MESOS_MASTER")
+            .index(1)
+        )
+        .arg(Arg::with_name("format")
+            .long("format")
+            .required(false)
+            .possible_values(&["table", "json"])
+            .default_value("table")
+            .help("Print a table or a JSON array")
+            .takes_value(true))
+
+}
+
+fn inspect_command(command: &str, matches: &ArgMatches) {
+    let (http, discovery) = parse_connection(matches);
+    let mesos_master = matches.value_of("mesos").unwrap();
+
+    let format = match matches.value_of("format") {
+        Some("json") => inspect::Format::Json,
+        _ => inspect::Format::Table
+    };
+
+    match command {
+        "agents" => inspect::agents(mesos_master, &parse_constraints(matches, "attr"), format, &http, &discovery),
+        "tasks" => inspect::tasks(mesos_master, matches.occurrences_of("all") > 0, format, &http, &discovery),
+        _ => inspect::frameworks(mesos_master, format, &http, &discovery)
+    };
+
+}
+
+fn job_command(command: &str, matches: &ArgMatches) {
+    let (http, discovery) = parse_connection(matches);
+    let mesos_master = matches.value_of("mesos").unwrap();
+    let handle = matches.value_of("HANDLE").unwrap();
+    let show_stderr = matches.occurrences_of("stderr") > 0;
+
+    match command {
+        "attach" => jobs::attach(mesos_master, handle, show_stderr, parse_chunk_size(matches), &http, &discovery),
+        "logs" => jobs::logs(mesos_master, handle, matches.occurrences_of("follow") > 0, show_stderr, parse_chunk_size(matches), &http, &discovery),
+        "wait" => jobs::wait(mesos_master, handle, &http, &discovery),
+        _ => jobs::kill(mesos_master, handle, &http, &discovery)
+    };
+
+}
+
+const OFFLINE_MASTER: &'static str = "<offline>";
+
+/**
+ * The master is the first positional argument, so clap would take the executor as the master when it's left
+ * out with --offline.  If the arguments don't parse as given they're parsed again with a placeholder master.
+ **/
+fn parse_args<'a, 'b>(app: App<'a, 'b>) -> ArgMatches<'a> {
+    let mut args: Vec<OsString> = env::args_os().collect();
+
+    let error = match app.clone().get_matches_from_safe(args.iter()) {
+        Ok(matches) => return matches,
+        Err(e) => e
+    };
+
+    let offline = args.iter()
+        .take_while(|arg| arg.to_str() != Some("--"))
+        .any(|arg| arg.to_str() == Some("--offline"));
+
+    if offline && error.kind != ErrorKind::HelpDisplayed && error.kind != ErrorKind::VersionDisplayed {
+        args.insert(1, OsString::from(OFFLINE_MASTER));
+
+        if let Ok(matches) = app.get_matches_from_safe(args) {
+            return matches;
+        }
+
     }
+
+    error.exit()
 }
 
 fn main() {
     let logger = env_logger::init();
 
     if logger.is_ok() {
-        let matches = App::new("Remote Executor")
+        let app = App::new("Remote Executor")
             .version("0.7.4")
             .author("Marc Dergacz. <marc@skytix.com.au>")
             .about("Synchronously execute tasks inside Mesos with STDOUT")
 
             .arg(Arg::with_name("mesos")
                 .required(true)
-                .help("Mesos master/zookeeper URL.  RExe will perform leader discovery if provided a zookeeper URL otherwise http[s] can be provided.  Eg. master1:2181,master2:2181,master3:2181/mesos or http://master1:5050")
+                .help("Mesos master/zookeeper URL.  RExe will perform leader discovery if provided a zookeeper URL otherwise one or more comma separated http[s] master URLs can be provided.  Eg. master1:2181,master2:2181,master3:2181/mesos, zk://user:password@master1:2181/mesos, http://master1:5050,http://master2:5050, dns://leader.mesos:5050 or srv://_leader._tcp.mesos.  Can be left out with --offline")
                 .value_name("MESOS_MASTER")
                 .index(1)
             )
@@ -270,7 +1080,127 @@ fn main() {
                 .short("a")
                 .required(false)
                 .multiple(true)
-                .help("Match an agent's attribute with the given value or pattern.  RExe will AND all attributes specified.  Eg. attribute=value or attribute=/value/ ")
+                .help("Placement constraint on an agent's attributes.  RExe will AND all constraints specified and OR alternatives separated by ||.  Eg. attribute=value, attribute=/value/, attribute>=2, attribute:IN:a,b, attribute:UNLIKE:pattern, attribute:MAX_PER:2 or attribute:GROUP_BY")
+                .takes_value(true))
+            .arg(Arg::with_name("strategy")
+                .long("strategy")
+                .required(false)
+                .possible_values(&["first-fit", "best-fit", "worst-fit", "spread", "random"])
+                .default_value("first-fit")
+                .help("How to choose between usable offers.  best-fit picks the agent with the least resources left over, worst-fit/spread the most.")
+                .takes_value(true))
+            .arg(Arg::with_name("offer_window")
+                .long("offer-window")
+                .value_name("MILLIS")
+                .required(false)
+                .help("Time to collect usable offers before choosing one.  Defaults to 0 for first-fit and 2000 otherwise.")
+                .takes_value(true))
+            .arg(Arg::with_name("docker_param")
+                .long("docker-param")
+                .value_name("KEY=VALUE")
+                .required(false)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Pass an arbitrary option to docker run.  Eg. --docker-param shm-size=1g.  Requires --allow-unsafe")
+                .takes_value(true))
+            .arg(Arg::with_name("privileged")
+                .long("privileged")
+                .required(false)
+                .help("Run the docker container in privileged mode.  Requires --allow-unsafe")
+                .takes_value(false))
+            .arg(Arg::with_name("cap_add")
+                .long("cap-add")
+                .value_name("CAPABILITY")
+                .required(false)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Add a Linux capability, eg. NET_ADMIN.  With the exec executor the listed capabilities become the task's only capabilities.  Requires --allow-unsafe")
+                .takes_value(true))
+            .arg(Arg::with_name("rlimit")
+                .long("rlimit")
+                .value_name("NAME=SOFT[:HARD]")
+                .required(false)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Set a resource limit, eg. nofile=4096:8192 or core=unlimited")
+                .takes_value(true))
+            .arg(Arg::with_name("allow_unsafe")
+                .long("allow-unsafe")
+                .required(false)
+                .help("Allow options that weaken the container's isolation")
+                .takes_value(false))
+            .arg(Arg::with_name("network")
+                .long("network")
+                .value_name("NETWORK")
+                .required(false)
+                .help("Network to run the task on.  host, bridge, none or the name of a Docker user defined network.  For the exec executor, the name of a CNI network")
+                .takes_value(true))
+            .arg(Arg::with_name("network_label")
+                .long("network-label")
+                .value_name("KEY=VALUE")
+                .required(false)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Label passed to the named network.  Eg. for CNI plugins")
+                .takes_value(true))
+            .arg(Arg::with_name("publish")
+                .short("p")
+                .long("publish")
+                .value_name("HOST:CONTAINER[/PROTOCOL]")
+                .required(false)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Map a port on the agent to a port in the container.  The host port must be offered by the agent")
+                .takes_value(true))
+            .arg(Arg::with_name("upload")
+                .long("upload")
+                .value_name("LOCAL:REMOTE")
+                .required(false)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Ship a local file into the task's sandbox before it starts.  REMOTE is relative to the sandbox and defaults to the file's name")
+                .takes_value(true))
+            .arg(Arg::with_name("upload_host")
+                .long("upload-host")
+                .value_name("HOST")
+                .required(false)
+                .help("Address Mesos agents can reach this host on to fetch uploads.  Defaults to the address used to reach the master")
+                .takes_value(true))
+            .arg(Arg::with_name("upload_port")
+                .long("upload-port")
+                .value_name("PORT")
+                .required(false)
+                .default_value("0")
+                .help("Port to serve uploads on.  0 picks a free port")
+                .takes_value(true))
+            .arg(Arg::with_name("collect")
+                .long("collect")
+                .value_name("GLOB")
+                .required(false)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Download sandbox files matching this pattern once the task finishes or fails.  Patterns are relative to the sandbox.  Eg. reports/*.xml")
+                .takes_value(true))
+            .arg(Arg::with_name("collect_dir")
+                .long("collect-dir")
+                .value_name("PATH")
+                .required(false)
+                .default_value(".")
+                .help("Local directory collected files are downloaded into")
+                .takes_value(true))
+            .arg(Arg::with_name("prefer")
+                .long("prefer")
+                .required(false)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Prefer agents matching this attribute constraint when choosing between usable offers.  Uses the same syntax as -a.")
+                .takes_value(true))
+            .arg(Arg::with_name("avoid")
+                .long("avoid")
+                .required(false)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Avoid agents matching all of these attribute constraints unless there is no other choice.  Uses the same syntax as -a.")
                 .takes_value(true))
             .arg(Arg::with_name("cpus")
                 .short("c")
@@ -320,6 +1250,18 @@ fn main() {
                 .required(false)
                 .help("Resource wait timeout. Time in seconds on how long RExe should wait for usable resource offers before giving up. Default: 60.  Set to <= 0 to wait indefinitely.")
                 .takes_value(true))
+            .arg(Arg::with_name("explain")
+                .long("explain")
+                .required(false)
+                .help("Print a summary of the offers received and why they were rejected once an offer is accepted.  The summary is always printed when -T expires.")
+                .takes_value(false))
+            .arg(Arg::with_name("max_launch_attempts")
+                .long("max-launch-attempts")
+                .value_name("ATTEMPTS")
+                .required(false)
+                .default_value("1")
+                .help("Number of times to launch the task if it is dropped or lost before it starts running")
+                .takes_value(true))
             .arg(Arg::with_name("shell")
                 .short("s")
                 .required(false)
@@ -329,11 +1271,20 @@ fn main() {
                 .short("v")
                 .required(false)
                 .multiple(true)
-                .help("Volume mapping - host_path:container_path:[RO|RW].  Eg.  /host/path:/container/path:RO  Defaults to RW access.")
+                .help("Volume mapping - host_path:container_path:[RO|RW].  Eg.  /host/path:/container/path:RO  Defaults to RW access.  Relative host paths are relative to the sandbox.")
+                .takes_value(true))
+            .arg(Arg::with_name("mount")
+                .long("mount")
+                .value_name("SPEC")
+                .required(false)
+                .multiple(true)
+                .number_of_values(1)
+                .help("Mount a volume - type=bind|sandbox|parent|volume|secret|image,src=SOURCE,dst=PATH[,ro][,driver=DRIVER][,opt=KEY=VALUE].  Eg. type=volume,src=cache,dst=/cache,driver=rexray")
                 .takes_value(true))
             .arg(Arg::with_name("verbose")
                 .long("verbose")
                 .required(false)
+                .global(true)
                 .help("Verbose output")
             )
             .arg(Arg::with_name("stderr")
@@ -341,25 +1292,322 @@ fn main() {
                 .required(false)
                 .help("Fetch STDERR as well")
             )
+            .arg(Arg::with_name("http_timeout")
+                .long("http-timeout")
+                .value_name("SECONDS")
+                .required(false)
+                .global(true)
+                .default_value("30")
+                .help("Timeout for each call made to Mesos masters and agents, covering connecting as well as waiting for the response.  Set to 0 to wait indefinitely.")
+                .takes_value(true))
+            .arg(Arg::with_name("zk_timeout")
+                .long("zk-timeout")
+                .value_name("SECONDS")
+                .required(false)
+                .global(true)
+                .default_value("15")
+                .help("Zookeeper session timeout.  Also how long to wait for a leader to be elected.")
+                .takes_value(true))
+            .arg(Arg::with_name("output_format")
+                .long("output-format")
+                .value_name("FORMAT")
+                .required(false)
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .help("json emits newline delimited JSON events describing the task's progress to STDERR, or --event-fd/--event-file.  The task's output and -v messages are unaffected")
+                .takes_value(true))
+            .arg(Arg::with_name("event_fd")
+                .long("event-fd")
+                .value_name("FD")
+                .required(false)
+                .conflicts_with("event_file")
+                .help("Write JSON events to this already open file descriptor")
+                .takes_value(true))
+            .arg(Arg::with_name("event_file")
+                .long("event-file")
+                .value_name("PATH")
+                .required(false)
+                .help("Append JSON events to this file")
+                .takes_value(true))
+            .arg(Arg::with_name("event_output")
+                .long("event-output")
+                .required(false)
+                .help("Include the task's STDOUT and STDERR as output events")
+                .takes_value(false))
+            .arg(Arg::with_name("follow_mode")
+                .long("follow-mode")
+                .value_name("MODE")
+                .required(false)
+                .possible_values(&["auto", "attach", "poll"])
+                .default_value("auto")
+                .help("How to follow the output of headless tasks.  attach streams it from the container, poll reads the sandbox files and auto attaches where the agent supports it")
+                .takes_value(true))
+            .arg(chunk_size_arg())
+            .arg(Arg::with_name("task_name")
+                .long("name")
+                .value_name("NAME")
+                .required(false)
+                .default_value("rexe-command")
+                .help("Name of the task in Mesos")
+                .takes_value(true))
+            .arg(Arg::with_name("prefix")
+                .long("prefix")
+                .value_name("FIELDS")
+                .required(false)
+                .help("Prefix each line of output with a comma separated list of time, name, task, host and stream.  Eg. --prefix time,host,stream")
+                .takes_value(true))
+            .arg(Arg::with_name("timestamps")
+                .long("timestamps")
+                .required(false)
+                .help("Prefix each line of output with the time it was read.  Same as --prefix time")
+                .takes_value(false))
+            .arg(Arg::with_name("stdout_file")
+                .long("stdout-file")
+                .value_name("PATH")
+                .required(false)
+                .help("Also write the task's STDOUT to a local file")
+                .takes_value(true))
+            .arg(Arg::with_name("stderr_file")
+                .long("stderr-file")
+                .value_name("PATH")
+                .required(false)
+                .help("Also write the task's STDERR to a local file.  STDERR is read even if --stderr isn't specified")
+                .takes_value(true))
+            .arg(Arg::with_name("log_file")
+                .long("log-file")
+                .value_name("PATH")
+                .required(false)
+                .help("Write the task's STDOUT and STDERR combined to a local file")
+                .takes_value(true))
+            .arg(Arg::with_name("max_file_size")
+                .long("max-file-size")
+                .value_name("SIZE")
+                .required(false)
+                .help("Rotate output files once they reach this size.  Accepts K, M and G suffixes.  Eg. 10M")
+                .takes_value(true))
+            .arg(Arg::with_name("max_files")
+                .long("max-files")
+                .value_name("COUNT")
+                .required(false)
+                .default_value("5")
+                .help("Number of rotated output files to keep, named <file>.1 to <file>.N.  0 truncates the file instead")
+                .takes_value(true))
+            .arg(Arg::with_name("follow_leader")
+                .long("follow-leader")
+                .required(false)
+                .help("Watch Zookeeper for leader changes and re-subscribe to the new leader if the master fails over while the task is running")
+                .takes_value(false))
+            .arg(Arg::with_name("master_scheme")
+                .long("master-scheme")
+                .required(false)
+                .global(true)
+                .possible_values(&["auto", "http", "https"])
+                .default_value("auto")
+                .help("Scheme used to talk to a master discovered via Zookeeper.  'auto' probes the master with HTTPS and falls back to HTTP.")
+                .takes_value(true))
+            .arg(Arg::with_name("ca_cert")
+                .long("ca-cert")
+                .value_name("PEM_FILE")
+                .required(false)
+                .global(true)
+                .help("PEM encoded CA certificate to trust when talking to masters and agents over HTTPS")
+                .takes_value(true))
+            .arg(Arg::with_name("client_cert")
+                .long("client-cert")
+                .value_name("PKCS12_FILE")
+                .required(false)
+                .global(true)
+                .help("PKCS#12 bundle containing the client certificate and private key used for mutual TLS")
+                .takes_value(true))
+            .arg(Arg::with_name("client_cert_password")
+                .long("client-cert-password")
+                .value_name("PASSWORD")
+                .required(false)
+                .global(true)
+                .requires("client_cert")
+                .help("Password protecting the --client-cert bundle")
+                .takes_value(true))
+            .arg(Arg::with_name("insecure_skip_hostname_verify")
+                .long("insecure-skip-hostname-verify")
+                .required(false)
+                .global(true)
+                .help("Skip verification of the hostname in certificates presented by masters and agents.  Combine with --ca-cert to trust self-signed certificates.")
+                .takes_value(false))
+            .arg(Arg::with_name("detach")
+                .long("detach")
+                .required(false)
+                .conflicts_with_all(&["tty", "collect"])
+                .help("Exit once the task is running and print a handle for the attach, logs, wait and kill commands.  The framework is checkpointed and kept for a week")
+                .takes_value(false))
+            .arg(Arg::with_name("dry_run")
+                .long("dry-run")
+                .required(false)
+                .conflicts_with("detach")
+                .help("Wait for a usable offer and print the ACCEPT call that would launch the task, then decline the offer and exit")
+                .takes_value(false))
+            .arg(Arg::with_name("offline")
+                .long("offline")
+                .required(false)
+                .conflicts_with("detach")
+                .help("Print the ACCEPT call without contacting a master.  Ids that come from the master are placeholders")
+                .takes_value(false))
+            .arg(Arg::with_name("health_check")
+                .long("health-check")
+                .value_name("TYPE:TARGET")
+                .required(false)
+                .help("Health check run by Mesos against the task.  Eg. http:8080/ready, https:8443, tcp:5432 or 'cmd:pg_isready -q'.  Ports are the ones the task listens on")
+                .takes_value(true))
+            .arg(Arg::with_name("health_interval")
+                .long("health-interval")
+                .value_name("SECONDS")
+                .required(false)
+                .default_value("10")
+                .help("Time between health checks")
+                .takes_value(true))
+            .arg(Arg::with_name("health_timeout")
+                .long("health-timeout")
+                .value_name("SECONDS")
+                .required(false)
+                .default_value("20")
+                .help("Time a health check may take before it counts as a failure")
+                .takes_value(true))
+            .arg(Arg::with_name("health_grace_period")
+                .long("health-grace-period")
+                .value_name("SECONDS")
+                .required(false)
+                .default_value("10")
+                .help("Time after the task starts during which failed health checks are ignored")
+                .takes_value(true))
+            .arg(Arg::with_name("health_retries")
+                .long("health-retries")
+                .value_name("COUNT")
+                .required(false)
+                .default_value("3")
+                .help("Consecutive failed health checks after which Mesos kills the task")
+                .takes_value(true))
+            .arg(Arg::with_name("on_unhealthy")
+                .long("on-unhealthy")
+                .required(false)
+                .possible_values(&["report", "kill", "fail"])
+                .default_value("report")
+                .help("What to do as soon as the task is reported unhealthy.  'kill' kills the task and follows it until it stops, 'fail' exits straight away with code 30")
+                .takes_value(true))
+            .arg(Arg::with_name("role")
+                .long("role")
+                .value_name("ROLE")
+                .required(false)
+                .global(true)
+                .help("Role to register the framework with.  Needed to reserve resources for a persistent volume")
+                .takes_value(true))
+            .arg(Arg::with_name("principal")
+                .long("principal")
+                .value_name("PRINCIPAL")
+                .required(false)
+                .global(true)
+                .help("Principal to register the framework with and to reserve resources as")
+                .takes_value(true))
+            .arg(Arg::with_name("persistent_volume")
+                .long("persistent-volume")
+                .value_name("ID:SIZE:PATH")
+                .required(false)
+                .requires("role")
+                .help("Run the task on a persistent volume that outlives it.  SIZE is in MB and PATH is relative to the sandbox.  The volume is reserved and created on the chosen agent the first time and later runs are launched on the agent holding it.  Requires --role")
+                .takes_value(true))
             .arg(Arg::with_name("ARGS")
                 .help("Image arguments")
                 .required(false)
                 .multiple(true)
                 .last(true)
             )
-            .get_matches();
+            .setting(AppSettings::SubcommandsNegateReqs)
+            .subcommand(SubCommand::with_name("destroy-volume")
+                .about("Destroy a persistent volume created with --persistent-volume and release the disk reserved for it")
+                .arg(Arg::with_name("mesos")
+                    .required(true)
+                    .help("Mesos master/zookeeper URL")
+                    .value_name("MESOS_MASTER")
+                    .index(1)
+                )
+                .arg(Arg::with_name("PERSISTENCE_ID")
+                    .required(true)
+                    .help("Id the volume was created with")
+                    .index(2)
+                )
+                .arg(Arg::with_name("timeout")
+                    .short("T")
+                    .required(false)
+                    .default_value("60")
+                    .help("Time in seconds to wait for the master to offer the volume back")
+                    .takes_value(true))
+            )
+            .subcommand(job_subcommand("attach", "Follow the output of a --detach task until it stops and exit with its result")
+                .arg(Arg::with_name("stderr")
+                    .long("stderr")
+                    .required(false)
+                    .help("Fetch STDERR as well"))
+                .arg(chunk_size_arg())
+            )
+            .subcommand(job_subcommand("logs", "Print the output of a --detach task")
+                .arg(Arg::with_name("follow")
+                    .short("f")
+                    .long("follow")
+                    .required(false)
+                    .help("Keep printing output until the task stops"))
+                .arg(Arg::with_name("stderr")
+                    .long("stderr")
+                    .required(false)
+                    .help("Fetch STDERR as well"))
+                .arg(chunk_size_arg())
+            )
+            .subcommand(job_subcommand("wait", "Wait for a --detach task to stop, print its final state and exit with its result"))
+            .subcommand(job_subcommand("kill", "Kill a --detach task"))
+            .subcommand(inspect_subcommand("agents", "List agents with their attributes and free/total resources")
+                .arg(Arg::with_name("attr")
+                    .short("a")
+                    .required(false)
+                    .multiple(true)
+                    .number_of_values(1)
+                    .help("Only list agents matching the constraint.  Takes the same expressions as -a when running a task")
+                    .takes_value(true))
+            )
+            .subcommand(inspect_subcommand("tasks", "List running tasks")
+                .arg(Arg::with_name("all")
+                    .long("all")
+                    .required(false)
+                    .help("Include completed and unreachable tasks"))
+            )
+            .subcommand(inspect_subcommand("frameworks", "List registered frameworks with their active tasks and resources"));
 
-        let mesos_master = matches.value_of("mesos").unwrap();
-        let task_info = generate_task_info(&matches);
+        let matches = parse_args(app);
 
-        if task_info.verbose_output {
-            println!("Executing task {}", mesos_master);
-        }
+        match matches.subcommand() {
+            ("destroy-volume", Some(sub_matches)) => destroy_volume(sub_matches),
+            (command @ "attach", Some(sub_matches)) |
+            (command @ "logs", Some(sub_matches)) |
+            (command @ "wait", Some(sub_matches)) |
+            (command @ "kill", Some(sub_matches)) => job_command(command, sub_matches),
+            (command @ "agents", Some(sub_matches)) |
+            (command @ "tasks", Some(sub_matches)) |
+            (command @ "frameworks", Some(sub_matches)) => inspect_command(command, sub_matches),
+            _ => {
+                let mesos_master = matches.value_of("mesos").unwrap();
+                let task_info = generate_task_info(&matches);
+
+                if matches.occurrences_of("offline") > 0 {
+                    scheduler::render_offline(&task_info);
+                    return;
+                }
 
-        scheduler::execute(
-            &mesos_master,
-            &task_info
-        );
+                if task_info.verbose_output {
+                    println!("Executing task {}", mesos_master);
+                }
+
+                scheduler::execute(
+                    &mesos_master,
+                    &task_info
+                );
+            }
+        };
 
     } else {
         error!("Unable to initialise logger");
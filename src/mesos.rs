@@ -1,13 +1,25 @@
+extern crate trust_dns_resolver;
 extern crate zookeeper;
 
-use hyper::header::{ContentType, Headers};
+use network;
 use reqwest;
+use network::RequestError;
+use self::trust_dns_resolver::Resolver;
 use self::zookeeper::{Acl, CreateMode, WatchedEvent, Watcher, ZooKeeper, KeeperState};
 use serde_json;
 use serde_json::Value;
-use std::time::Duration;
+use std::net::ToSocketAddrs;
+use std::time::{Duration, Instant};
 use std::process::exit;
 use std::sync::{Arc, Mutex};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread;
+use types::{DiscoveryConfig, HttpConfig, MasterScheme};
+
+const LEADER_NODE_PREFIX: &'static str = "json.info_";
+const DEFAULT_MASTER_PORT: u16 = 5050;
+const DNS_POLL_SECONDS: u64 = 5;
 
 struct WatcherStateHandler {
     state: KeeperState
@@ -37,30 +49,310 @@ impl Watcher for ConnectionWatcher {
 
 }
 
-fn is_ssl_enabled(master_host: &str) -> bool {
+fn is_ssl_enabled(master_host: &str, http: &HttpConfig) -> bool {
     // We will attempt to access the /version endpoint with https first.  If that fails, fallback to HTTP.
     // Am not aware of any other mechanism to get the scheme from Zookeeper.
     let mut https_url = String::from("https://");
     https_url.push_str(master_host);
     https_url.push_str("/version");
 
-    let mut headers = Headers::new();
+    let client = network::create_json_client(http);
 
-    headers.set(ContentType::json());
+    match client.get(https_url.as_str()).send() {
+        Ok(_) => true,
+        Err(e) => {
+            info!("HTTPS probe of {} failed, falling back to HTTP: {}", master_host, e);
+            false
+        }
+    }
 
-    match reqwest::get(https_url.as_str()) {
-        Ok(response) => true,
-        _ => false
+}
+
+fn get_master_scheme(master_host: &str, http: &HttpConfig, scheme: &MasterScheme) -> &'static str {
+
+    match *scheme {
+        MasterScheme::Http => "http://",
+        MasterScheme::Https => "https://",
+        MasterScheme::Auto => match is_ssl_enabled(master_host, http) {
+            true => "https://",
+            _ => "http://"
+        }
     }
 
 }
 
-pub fn discover_mesos_leader(master_config: &str) -> String {
-    // Unless explicitly specified, we will assume any URL specified as the mesos master is the ZK ensemble.
-    if master_config.starts_with("http") {
-        return String::from(master_config);
+/**
+ * Asks each of the masters in a comma separated list where the leader is.  Masters that aren't leading
+ * redirect /master/redirect to the leader.
+ **/
+fn find_http_leader(master_config: &str, http: &HttpConfig) -> String {
+    let mut masters: Vec<String> = vec![];
+    let mut default_scheme = String::from("http://");
 
-    } else {
+    for master in master_config.split(',').map(|master| master.trim()).filter(|master| !master.is_empty()) {
+
+        match master.find("://") {
+            Some(index) => {
+                default_scheme = String::from(&master[..index + 3]);
+                masters.push(String::from(master.trim_right_matches('/')));
+            },
+            None => {
+                let mut url = default_scheme.clone();
+                url.push_str(master.trim_right_matches('/'));
+                masters.push(url);
+            }
+        };
+
+    }
+
+    let client = network::create_json_client(http);
+
+    for master in &masters {
+        let mut redirect_url = master.clone();
+        redirect_url.push_str("/master/redirect");
+
+        match network::get(&client, redirect_url.as_str()) {
+            Err(RequestError::Redirect(location)) => {
+                let leader = network::base_url(network::resolve_location(redirect_url.as_str(), location.as_str()).as_str());
+                info!("Master {} reports the leader is {}", master, leader);
+                return leader;
+            },
+            Ok(_) => warn!("Master {} did not redirect to a leader", master),
+            Err(e) => warn!("Unable to find leader through {}: {}", master, e)
+        };
+
+    }
+
+    if masters.len() == 1 {
+        // Older masters may not have the redirect endpoint.  Let the subscribe call find out.
+        return masters[0].clone();
+    }
+
+    error!("Unable to find the leading Mesos master among: {}", master_config);
+    exit(1);
+}
+
+pub struct ZookeeperUrl {
+    pub hosts: String,
+    pub path: String,
+    // user:password for digest authentication.
+    pub auth: Option<String>
+}
+
+/**
+ * Parses zk://[user:password@]host1:2181,host2:2181[/path].  The zk:// prefix is optional.
+ **/
+pub fn parse_zookeeper_url(master_config: &str) -> Result<ZookeeperUrl, String> {
+    let mut remainder = master_config.trim();
+
+    if remainder.starts_with("zk://") {
+        remainder = &remainder[5..];
+    }
+
+    let auth = match remainder.rfind('@') {
+        Some(index) => {
+            let credentials = &remainder[..index];
+            remainder = &remainder[index + 1..];
+
+            if !credentials.contains(':') {
+                return Err(format!("Zookeeper credentials in {} must be in the form user:password", master_config));
+            }
+
+            Some(String::from(credentials))
+        },
+        None => None
+    };
+
+    let (hosts, path) = match remainder.find('/') {
+        Some(index) => (&remainder[..index], &remainder[index..]),
+        None => (remainder, "/")
+    };
+
+    if hosts.is_empty() {
+        return Err(format!("No Zookeeper hosts found in {}", master_config));
+    }
+
+    let path = match path.trim_right_matches('/') {
+        "" => "/",
+        path => path
+    };
+
+    Ok(ZookeeperUrl {
+        hosts: String::from(hosts),
+        path: String::from(path),
+        auth
+    })
+}
+
+fn connect_zookeeper(url: &ZookeeperUrl, timeout: Duration, state: Arc<Mutex<WatcherStateHandler>>) -> Result<ZooKeeper, String> {
+    let watcher = ConnectionWatcher { handler: state };
+
+    let zk_client = match ZooKeeper::connect(url.hosts.as_str(), timeout, watcher) {
+        Ok(zk_client) => zk_client,
+        Err(e) => return Err(format!("Unable to connect to Zookeeper cluster {}: {:?}", url.hosts, e))
+    };
+
+    if let Some(ref auth) = url.auth {
+
+        if let Err(e) = zk_client.add_auth("digest", auth.as_bytes().to_vec()) {
+            return Err(format!("Unable to authenticate with Zookeeper cluster {}: {:?}", url.hosts, e));
+        }
+
+    }
+
+    Ok(zk_client)
+}
+
+fn node_path(parent: &str, node: &str) -> String {
+    let mut path = String::from(parent.trim_right_matches('/'));
+    path.push_str("/");
+    path.push_str(node);
+    path
+}
+
+/**
+ * The MasterInfo JSON written by Mesos has carried the address in a few places over the releases.
+ **/
+fn parse_master_info(data: &[u8]) -> Result<String, String> {
+
+    let value: Value = match serde_json::from_slice(data) {
+        Ok(value) => value,
+        Err(e) => return Err(format!("{}", e))
+    };
+
+    let hostname = value["hostname"].as_str()
+        .or(value["address"]["hostname"].as_str())
+        .or(value["address"]["ip"].as_str());
+
+    let port = value["port"].as_i64().or(value["address"]["port"].as_i64());
+
+    match (hostname, port) {
+        (Some(hostname), Some(port)) => Ok(format!("{}:{}", hostname, port)),
+        (None, _) => Err(String::from("No hostname or address found")),
+        (_, None) => Err(String::from("No port found"))
+    }
+
+}
+
+/**
+ * The leading master is the json.info_ node with the lowest sequence number.  Returns None if no master has
+ * been elected yet.
+ **/
+fn read_zookeeper_leader(zk_client: &ZooKeeper, path: &str) -> Result<Option<String>, String> {
+
+    let children = match zk_client.get_children(path, false) {
+        Ok(children) => children,
+        Err(e) => return Err(format!("Unable to list {} in Zookeeper: {:?}", path, e))
+    };
+
+    let leader_node = children.iter()
+        .filter(|node| node.starts_with(LEADER_NODE_PREFIX))
+        .min_by_key(|node| node[LEADER_NODE_PREFIX.len()..].parse::<u64>().unwrap_or(u64::max_value()));
+
+    match leader_node {
+        Some(node) => {
+            let leader_path = node_path(path, node.as_str());
+
+            let node_content = match zk_client.get_data(leader_path.as_str(), false) {
+                Ok(data) => data.0,
+                Err(e) => return Err(format!("Unable to read {} from Zookeeper: {:?}", leader_path, e))
+            };
+
+            match parse_master_info(&node_content[..]) {
+                Ok(leader) => Ok(Some(leader)),
+                Err(e) => Err(format!("Leader node {} is not valid: {}", leader_path, e))
+            }
+
+        },
+        None => Ok(None)
+    }
+
+}
+
+fn close_zookeeper(zk_client: ZooKeeper, state: Arc<Mutex<WatcherStateHandler>>) {
+
+    match state.lock().unwrap().get_state() {
+        KeeperState::SyncConnected | KeeperState::ConnectedReadOnly => {
+            zk_client.close();
+        },
+        _ => {}
+    };
+
+}
+
+fn find_zookeeper_leader(master_config: &str, http: &HttpConfig, discovery: &DiscoveryConfig) -> String {
+
+    let url = match parse_zookeeper_url(master_config) {
+        Ok(url) => url,
+        Err(e) => {
+            error!("{}", e);
+            exit(1);
+        }
+    };
+
+    let shared_state = Arc::new(
+        Mutex::new(
+            WatcherStateHandler { state: KeeperState::Disconnected }
+        )
+    );
+
+    let zk_client = match connect_zookeeper(&url, discovery.zk_timeout, shared_state.clone()) {
+        Ok(zk_client) => zk_client,
+        Err(e) => {
+            error!("{}", e);
+            exit(1);
+        }
+    };
+
+    // A new leader may be in the middle of being elected, so give it until the session timeout to appear.
+    let deadline = Instant::now() + discovery.zk_timeout;
+
+    let leader = loop {
+
+        match read_zookeeper_leader(&zk_client, url.path.as_str()) {
+            Ok(Some(leader)) => break leader,
+            Ok(None) if Instant::now() < deadline => {
+                info!("No Mesos leader registered in Zookeeper yet.  Retrying.");
+                thread::sleep(Duration::from_secs(1));
+            },
+            Ok(None) => {
+                error!("Unable to find any Mesos leaders in Zookeeper: {}", master_config);
+                exit(1);
+            },
+            Err(e) => {
+                error!("{}", e);
+                exit(1);
+            }
+        };
+
+    };
+
+    close_zookeeper(zk_client, shared_state);
+
+    let mut leader_uri = String::from(get_master_scheme(leader.as_str(), http, &discovery.master_scheme));
+    leader_uri.push_str(leader.as_str());
+    leader_uri
+}
+
+/**
+ * Keeps a Zookeeper session open and sends the URL of the leading master every time it changes, starting with
+ * the current leader.
+ **/
+fn watch_zookeeper_leader(master_config: &str, http: &HttpConfig, discovery: &DiscoveryConfig) -> Receiver<String> {
+
+    let url = match parse_zookeeper_url(master_config) {
+        Ok(url) => url,
+        Err(e) => {
+            error!("{}", e);
+            exit(1);
+        }
+    };
+
+    let (leader_tx, leader_rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let http = http.clone();
+    let discovery = discovery.clone();
+
+    thread::spawn(move || {
 
         let shared_state = Arc::new(
             Mutex::new(
@@ -68,71 +360,324 @@ pub fn discover_mesos_leader(master_config: &str) -> String {
             )
         );
 
-        let watcher = ConnectionWatcher { handler: shared_state.clone() };
-        let zk = zookeeper::ZooKeeper::connect(master_config, Duration::from_secs(15), watcher);
-
-        let zk_client = match zk {
-
-            Ok(zk_client) => {
-                zk_client
-            },
+        let zk_client = match connect_zookeeper(&url, discovery.zk_timeout, shared_state.clone()) {
+            Ok(zk_client) => zk_client,
             Err(e) => {
-                error!("Unable to connect to Zookeeper cluster: {}\n{}", master_config, e);
-                exit(1);
+                warn!("Unable to watch for Mesos leader changes: {}", e);
+                return;
             }
         };
 
-        let mut children = zk_client.get_children("/", false).unwrap();
-        children.sort();
+        let mut current_leader: Option<String> = None;
+
+        loop {
+            let (notify_tx, notify_rx): (Sender<()>, Receiver<()>) = mpsc::channel();
+            let notify_tx = Mutex::new(notify_tx);
 
-        let mut leader: Option<String> = None;
+            let watch = zk_client.get_children_w(url.path.as_str(), move |_event: WatchedEvent| {
+                let _ = notify_tx.lock().unwrap().send(());
+            });
 
-        for node in children {
+            if let Err(e) = watch {
+                warn!("Unable to watch {} in Zookeeper: {:?}", url.path, e);
+                thread::sleep(Duration::from_secs(1));
+                continue;
+            }
 
-            if node.starts_with("json.info_") {
-                let mut path = String::from("/");
-                path.push_str(node.as_str());
+            match read_zookeeper_leader(&zk_client, url.path.as_str()) {
+                Ok(Some(leader)) => {
 
-                let node_content = zk_client.get_data(path.as_str(), false).unwrap().0;
-                let node_json = String::from_utf8(node_content).unwrap();
+                    if current_leader.as_ref() != Some(&leader) {
+                        let mut leader_uri = String::from(get_master_scheme(leader.as_str(), &http, &discovery.master_scheme));
+                        leader_uri.push_str(leader.as_str());
 
-                let value: Value = serde_json::from_str(node_json.as_str()).unwrap();
+                        info!("Mesos leader is now {}", leader_uri);
+                        current_leader = Some(leader);
 
-                let mut leader_host = String::from(value["hostname"].as_str().unwrap());
-                leader_host.push_str(":");
-                leader_host.push_str(value["port"].as_i64().unwrap().to_string().as_str());
+                        if leader_tx.send(leader_uri).is_err() {
+                            break;
+                        }
 
-                let mut leader_uri = String::from(
-                    match is_ssl_enabled(leader_host.as_str()) {
-                        true => "https://",
-                        _ => "http://"
                     }
-                );
 
-                leader_uri.push_str(leader_host.as_str());
-                leader = Some(leader_uri);
+                },
+                Ok(None) => info!("No Mesos leader registered in Zookeeper"),
+                Err(e) => warn!("{}", e)
+            };
+
+            if notify_rx.recv().is_err() {
                 break;
             }
 
         }
 
-        match shared_state.lock().unwrap().get_state() {
-            KeeperState::SyncConnected | KeeperState::ConnectedReadOnly => {
-                zk_client.close();
+        close_zookeeper(zk_client, shared_state);
+    });
+
+    leader_rx
+}
+
+/**
+ * Resolves a mesos-dns name to the leading master.  dns:// names are resolved through A records and used as
+ * they are, so the name keeps following the leader and matches TLS certificates.  srv:// names are looked up as
+ * SRV records, eg. srv://_leader._tcp.mesos, and the target with the lowest priority is used.
+ *
+ * Returns the address to connect to and what it resolved to, which changes when the leader does.
+ **/
+fn resolve_dns_leader(name: &str, port: u16, srv: bool) -> Result<(String, String), String> {
+
+    if srv {
+        let resolver = match Resolver::from_system_conf() {
+            Ok(resolver) => resolver,
+            Err(e) => return Err(format!("Unable to read the system DNS configuration: {}", e))
+        };
+
+        let records = match resolver.lookup_srv(name) {
+            Ok(records) => records,
+            Err(e) => return Err(format!("Unable to look up SRV record {}: {}", name, e))
+        };
+
+        match records.iter().min_by_key(|record| record.priority()) {
+            Some(record) => {
+                let address = format!("{}:{}", record.target().to_string().trim_right_matches('.'), record.port());
+                Ok((address.clone(), address))
+            },
+            None => Err(format!("No SRV records found for {}", name))
+        }
+
+    } else {
+
+        match (name, port).to_socket_addrs() {
+            Ok(mut addresses) => match addresses.next() {
+                Some(resolved) => Ok((format!("{}:{}", name, port), resolved.to_string())),
+                None => Err(format!("No addresses found for {}", name))
             },
-            _ => {}
+            Err(e) => Err(format!("Unable to resolve {}: {}", name, e))
+        }
+
+    }
+
+}
+
+/**
+ * Finds the leading Mesos master.  Implemented for Zookeeper, mesos-dns and plain HTTP master URLs.
+ **/
+pub trait LeaderDiscovery {
+
+    /**
+     * Returns the base URL of the leading master, eg. http://master1:5050.
+     **/
+    fn discover(&self) -> String;
+
+    /**
+     * Sends the base URL of the leading master every time it changes, starting with the current leader.
+     * Returns None if the backend has no way of noticing a change.
+     **/
+    fn watch(&self) -> Option<Receiver<String>>;
+
+}
+
+pub struct HttpDiscovery {
+    masters: String,
+    http: HttpConfig
+}
+
+pub struct ZookeeperDiscovery {
+    master_config: String,
+    http: HttpConfig,
+    discovery: DiscoveryConfig
+}
+
+pub struct DnsDiscovery {
+    name: String,
+    port: u16,
+    srv: bool,
+    http: HttpConfig,
+    discovery: DiscoveryConfig
+}
+
+impl LeaderDiscovery for HttpDiscovery {
+
+    fn discover(&self) -> String {
+        find_http_leader(self.masters.as_str(), &self.http)
+    }
+
+    fn watch(&self) -> Option<Receiver<String>> {
+        None
+    }
+
+}
+
+impl LeaderDiscovery for ZookeeperDiscovery {
+
+    fn discover(&self) -> String {
+        find_zookeeper_leader(self.master_config.as_str(), &self.http, &self.discovery)
+    }
+
+    fn watch(&self) -> Option<Receiver<String>> {
+        Some(watch_zookeeper_leader(self.master_config.as_str(), &self.http, &self.discovery))
+    }
+
+}
+
+impl DnsDiscovery {
+
+    fn parse(master_config: &str, http: &HttpConfig, discovery: &DiscoveryConfig) -> Result<DnsDiscovery, String> {
+        let (srv, remainder) = match master_config.find("://") {
+            Some(index) => (&master_config[..index] == "srv", &master_config[index + 3..]),
+            None => (false, master_config)
         };
 
-        match leader {
-            Some(leader) => {
-                return leader;
+        let remainder = remainder.trim_right_matches('/');
+
+        let (name, port) = match remainder.rfind(':') {
+            Some(index) => match remainder[index + 1..].parse::<u16>() {
+                Ok(port) => (&remainder[..index], port),
+                Err(_) => return Err(format!("Invalid port in {}", master_config))
             },
-            _ => {
-                error!("Unable to find any Mesos leaders in Zookeeper: {}", master_config);
+            None => (remainder, DEFAULT_MASTER_PORT)
+        };
+
+        if name.is_empty() {
+            return Err(format!("No DNS name found in {}", master_config));
+        }
+
+        Ok(DnsDiscovery {
+            name: String::from(name),
+            port,
+            srv,
+            http: http.clone(),
+            discovery: discovery.clone()
+        })
+    }
+
+    fn find_leader(name: &str, port: u16, srv: bool, http: &HttpConfig, discovery: &DiscoveryConfig) -> Result<String, String> {
+        let (leader, _) = resolve_dns_leader(name, port, srv)?;
+
+        let mut leader_uri = String::from(get_master_scheme(leader.as_str(), http, &discovery.master_scheme));
+        leader_uri.push_str(leader.as_str());
+
+        Ok(leader_uri)
+    }
+
+}
+
+impl LeaderDiscovery for DnsDiscovery {
+
+    fn discover(&self) -> String {
+
+        match DnsDiscovery::find_leader(self.name.as_str(), self.port, self.srv, &self.http, &self.discovery) {
+            Ok(leader) => leader,
+            Err(e) => {
+                error!("Unable to find the leading Mesos master: {}", e);
                 exit(1);
             }
-        };
+        }
 
     }
 
-}
\ No newline at end of file
+    fn watch(&self) -> Option<Receiver<String>> {
+        let (leader_tx, leader_rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+        let name = self.name.clone();
+        let port = self.port;
+        let srv = self.srv;
+        let http = self.http.clone();
+        let discovery = self.discovery.clone();
+
+        // There is nothing to watch in DNS, so poll it.  Masters of a cluster share a scheme, so it is only probed
+        // for the first leader found.  A dns:// name stays the same when the leader changes, so changes are spotted
+        // by what it resolves to.
+        thread::spawn(move || {
+            let mut current_leader: Option<String> = None;
+            let mut scheme: Option<&'static str> = None;
+
+            loop {
+
+                match resolve_dns_leader(name.as_str(), port, srv) {
+                    Ok((address, resolved)) => {
+
+                        if current_leader.as_ref() != Some(&resolved) {
+                            let leader_scheme = *scheme.get_or_insert_with(|| get_master_scheme(address.as_str(), &http, &discovery.master_scheme));
+
+                            let mut leader = String::from(leader_scheme);
+                            leader.push_str(address.as_str());
+
+                            info!("Mesos leader is now {} ({})", leader, resolved);
+                            current_leader = Some(resolved);
+
+                            if leader_tx.send(leader).is_err() {
+                                break;
+                            }
+
+                        }
+
+                    },
+                    Err(e) => warn!("{}", e)
+                };
+
+                thread::sleep(Duration::from_secs(DNS_POLL_SECONDS));
+            }
+
+        });
+
+        Some(leader_rx)
+    }
+
+}
+
+/**
+ * Unless explicitly specified, we will assume any URL specified as the mesos master is the ZK ensemble.
+ **/
+pub fn create_leader_discovery(master_config: &str, http: &HttpConfig, discovery: &DiscoveryConfig) -> Box<LeaderDiscovery> {
+
+    if master_config.starts_with("http") {
+
+        Box::new(HttpDiscovery {
+            masters: String::from(master_config),
+            http: http.clone()
+        })
+
+    } else if master_config.starts_with("dns://") || master_config.starts_with("srv://") {
+
+        match DnsDiscovery::parse(master_config, http, discovery) {
+            Ok(dns_discovery) => Box::new(dns_discovery),
+            Err(e) => {
+                error!("{}", e);
+                exit(1);
+            }
+        }
+
+    } else {
+
+        Box::new(ZookeeperDiscovery {
+            master_config: String::from(master_config),
+            http: http.clone(),
+            discovery: discovery.clone()
+        })
+
+    }
+
+}
+
+/**
+ * Sends a call to the master's operator API, following a redirect to the leading master.
+ **/
+pub fn operator_call(client: &reqwest::Client, master_url: &str, body: &Value) -> Result<reqwest::Response, RequestError> {
+    let mut api_url = String::from(master_url);
+    api_url.push_str("/api/v1");
+
+    match network::post(client, api_url.as_str(), body.to_string()) {
+        Err(RequestError::Redirect(location)) => {
+            network::post(client, network::resolve_location(api_url.as_str(), location.as_str()).as_str(), body.to_string())
+        },
+        result => result
+    }
+
+}
+
+/**
+ * An operator API call with a JSON response, eg. GET_TASKS.
+ **/
+pub fn operator_query(client: &reqwest::Client, master_url: &str, body: &Value) -> Result<Value, RequestError> {
+    operator_call(client, master_url, body).and_then(|mut response| network::read_json(&mut response))
+}
@@ -1,9 +1,233 @@
+use std::fmt;
 use std::str::from_utf8;
 use std::process::exit;
+use std::io;
 use std::io::Read;
+use std::fs::File;
+use std::time::Duration;
 
 use hyper::header::{Headers, ContentType};
 use reqwest;
+use reqwest::StatusCode;
+use serde::de::DeserializeOwned;
+use serde_json;
+use types::HttpConfig;
+
+pub enum RequestError {
+    Transport(reqwest::Error),
+    Status(StatusCode, String),
+    Redirect(String),
+    Decode(String)
+}
+
+impl fmt::Display for RequestError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        match *self {
+            RequestError::Transport(ref e) => write!(f, "{}", e),
+            RequestError::Status(ref status, ref body) => match body.trim().is_empty() {
+                true => write!(f, "Request failed with {}", status),
+                false => write!(f, "Request failed with {}: {}", status, body.trim())
+            },
+            RequestError::Redirect(ref location) => write!(f, "Redirected to {}", location),
+            RequestError::Decode(ref message) => write!(f, "Unable to decode response: {}", message)
+        }
+
+    }
+
+}
+
+fn read_file_contents(path: &str) -> Vec<u8> {
+    let mut buffer: Vec<u8> = vec![];
+
+    match File::open(path) {
+        Ok(mut file) => {
+
+            if let Err(e) = file.read_to_end(&mut buffer) {
+                error!("Unable to read {}: {}", path, e);
+                exit(1);
+            }
+
+        },
+        Err(e) => {
+            error!("Unable to open {}: {}", path, e);
+            exit(1);
+        }
+    };
+
+    buffer
+}
+
+/**
+ * Builds a client that trusts the configured CA bundle and presents the client identity for mutual TLS.
+ * Clients keep their connections alive, so create them once and share them rather than building one per call.
+ **/
+fn build_client(http: &HttpConfig, headers: Headers, timeout: Option<Duration>) -> reqwest::Client {
+    let tls = &http.tls;
+    let mut builder = reqwest::ClientBuilder::new();
+
+    builder.default_headers(headers);
+    builder.timeout(timeout);
+
+    // Masters answer with a 307 when they aren't the leader.  Callers follow these themselves so a POST body
+    // is never dropped on the way and the new leader can be remembered for later calls.
+    builder.redirect(reqwest::RedirectPolicy::none());
+
+    match tls.ca_cert {
+        Some(ref path) => {
+
+            match reqwest::Certificate::from_pem(&read_file_contents(path)[..]) {
+                Ok(certificate) => {
+                    builder.add_root_certificate(certificate);
+                },
+                Err(e) => {
+                    error!("Unable to load CA certificate {}: {}", path, e);
+                    exit(1);
+                }
+            };
+
+        },
+        None => {}
+    };
+
+    match tls.client_cert {
+        Some(ref path) => {
+
+            match reqwest::Identity::from_pkcs12_der(&read_file_contents(path)[..], tls.client_cert_password.as_str()) {
+                Ok(identity) => {
+                    builder.identity(identity);
+                },
+                Err(e) => {
+                    error!("Unable to load client certificate {}: {}", path, e);
+                    exit(1);
+                }
+            };
+
+        },
+        None => {}
+    };
+
+    if tls.insecure_skip_hostname_verify {
+        builder.danger_disable_hostname_verification();
+    }
+
+    match builder.build() {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Unable to create HTTP client: {}", e);
+            exit(1);
+        }
+    }
+
+}
+
+pub fn create_client(http: &HttpConfig, headers: Headers) -> reqwest::Client {
+    build_client(http, headers, http.timeout)
+}
+
+/**
+ * Clients for long lived responses such as the scheduler event stream, which must not be timed out.
+ **/
+pub fn create_streaming_client(http: &HttpConfig, headers: Headers) -> reqwest::Client {
+    build_client(http, headers, None)
+}
+
+pub fn create_json_client(http: &HttpConfig) -> reqwest::Client {
+    let mut headers = Headers::new();
+
+    headers.set(ContentType::json());
+
+    create_client(http, headers)
+}
+
+pub fn check_status(mut response: reqwest::Response) -> Result<reqwest::Response, RequestError> {
+
+    if response.status().is_success() {
+        Ok(response)
+
+    } else if response.status().is_redirection() {
+
+        match get_header_string_value("Location", response.headers()) {
+            Some(ref location) if !location.is_empty() => Err(RequestError::Redirect(location.clone())),
+            _ => Err(RequestError::Status(response.status(), String::from("Redirect without a Location header")))
+        }
+
+    } else {
+        let body = response.text().unwrap_or(String::from(""));
+        Err(RequestError::Status(response.status(), body))
+    }
+
+}
+
+pub fn post(client: &reqwest::Client, url: &str, body: String) -> Result<reqwest::Response, RequestError> {
+
+    match client.post(url).body(body).send() {
+        Ok(response) => check_status(response),
+        Err(e) => Err(RequestError::Transport(e))
+    }
+
+}
+
+pub fn get(client: &reqwest::Client, url: &str) -> Result<reqwest::Response, RequestError> {
+
+    match client.get(url).send() {
+        Ok(response) => check_status(response),
+        Err(e) => Err(RequestError::Transport(e))
+    }
+
+}
+
+/**
+ * Resolves the Location of a redirect against the URL that was requested.  Mesos masters redirect to
+ * scheme relative locations such as //leader:5050/api/v1/scheduler.
+ **/
+pub fn resolve_location(current_url: &str, location: &str) -> String {
+
+    if location.starts_with("http://") || location.starts_with("https://") {
+        return String::from(location);
+    }
+
+    let scheme_end = match current_url.find("://") {
+        Some(index) => index + 3,
+        None => 0
+    };
+
+    if location.starts_with("//") {
+        return format!("{}{}", &current_url[..scheme_end], &location[2..]);
+    }
+
+    format!("{}{}", base_url(current_url), location)
+}
+
+/**
+ * Strips the path from a URL, leaving scheme://host:port.
+ **/
+pub fn base_url(url: &str) -> String {
+
+    let scheme_end = match url.find("://") {
+        Some(index) => index + 3,
+        None => 0
+    };
+
+    match url[scheme_end..].find('/') {
+        Some(index) => String::from(&url[..scheme_end + index]),
+        None => String::from(url)
+    }
+
+}
+
+pub fn read_json<T: DeserializeOwned>(response: &mut reqwest::Response) -> Result<T, RequestError> {
+
+    match response.text() {
+        Ok(content) => match serde_json::from_str(content.as_str()) {
+            Ok(value) => Ok(value),
+            Err(e) => Err(RequestError::Decode(format!("{}", e)))
+        },
+        Err(e) => Err(RequestError::Transport(e))
+    }
+
+}
 
 pub fn get_header_string_value<'a>(name: &'a str, headers: &'a Headers) -> Option<String> {
     let header_option = headers.get_raw(name);
@@ -30,7 +254,11 @@ pub fn get_header_string_value<'a>(name: &'a str, headers: &'a Headers) -> Optio
     Some(value)
 }
 
-pub fn read_next_message(response: &mut reqwest::Response) -> String {
+/**
+ * Reads the next RecordIO message from a streaming response.  Errors when the stream is closed or broken,
+ * which callers should treat as losing the connection.
+ **/
+pub fn read_next_message(response: &mut reqwest::Response) -> io::Result<String> {
     let mut msg_length_str = String::from("");
     let mut buffer: String = String::from("");
     let mut have_msg_length = false;
@@ -38,10 +266,13 @@ pub fn read_next_message(response: &mut reqwest::Response) -> String {
     while !have_msg_length {
         let mut buf: Vec<u8> = vec![0; 1];
 
-        match response.read(&mut buf[..]) {
+        match response.read(&mut buf[..])? {
 
-            Ok(_) => {
-                let resp_str = from_utf8(&buf).unwrap();
+            0 => {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "Stream closed by remote end"));
+            },
+            _ => {
+                let resp_str = from_utf8(&buf).unwrap_or("");
 
                 for c in resp_str.chars() {
 
@@ -61,34 +292,27 @@ pub fn read_next_message(response: &mut reqwest::Response) -> String {
 
                 }
 
-            },
-            Err(e) => {
-                error!("{}", e);
-                exit(1);
             }
 
         };
 
     }
 
-    let msg_len = msg_length_str.parse::<usize>().unwrap() - buffer.len();
-    let mut buf: Vec<u8> = vec![0; msg_len];
-
-    match response.read_exact(&mut buf[..]) {
+    let msg_len = match msg_length_str.trim().parse::<usize>() {
+        Ok(length) => length - buffer.len(),
+        Err(_) => {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Invalid record length: {}", msg_length_str)));
+        }
+    };
 
-        Ok(_) => {
+    let mut buf: Vec<u8> = vec![0; msg_len];
 
-            match from_utf8(&buf) {
-                Ok(value) => buffer.push_str(value),
-                Err(_) => {}
-            }
+    response.read_exact(&mut buf[..])?;
 
-        },
-        Err(e) => {
-            error!("{}", e);
-            exit(1);
-        }
+    match from_utf8(&buf) {
+        Ok(value) => buffer.push_str(value),
+        Err(_) => {}
     }
 
-    buffer
-}
\ No newline at end of file
+    Ok(buffer)
+}
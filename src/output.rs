@@ -0,0 +1,356 @@
+use chrono::Local;
+use events::{EventLog, OutputEventWriter};
+use std::fs;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{stderr, stdout, Write};
+use std::sync::{Arc, Mutex};
+use types::{OutputConfig, PrefixField};
+
+// Partial lines longer than this are written out without waiting for the rest of the line.
+const MAX_LINE_BUFFER: usize = 65536;
+
+/**
+ * Writers used to keep a local copy of the task's output alongside what is streamed to the terminal.
+ **/
+
+pub struct RotatingFileWriter {
+    path: String,
+    file: File,
+    size: u64,
+    max_size: Option<u64>,
+    max_files: u32
+}
+
+/**
+ * Lets the stdout and stderr readers write to the same combined log.
+ **/
+#[derive(Clone)]
+pub struct SharedWriter {
+    inner: Arc<Mutex<RotatingFileWriter>>
+}
+
+pub struct TeeWriter {
+    writers: Vec<Box<Write + Send>>
+}
+
+/**
+ * What is known about the task when its console is created, for prefixing lines.
+ **/
+pub struct LineContext {
+    pub task_name: String,
+    pub task_id: String,
+    pub hostname: String
+}
+
+/**
+ * Buffers output until a whole line is available and writes it with a prefix, so lines split across reads
+ * are only prefixed once and lines from different streams don't interleave.
+ **/
+pub struct LinePrefixWriter {
+    inner: Box<Write + Send>,
+    fields: Vec<PrefixField>,
+    task_name: String,
+    task_id: String,
+    hostname: String,
+    stream: &'static str,
+    buffer: Vec<u8>
+}
+
+impl RotatingFileWriter {
+
+    pub fn open(path: &str, max_size: Option<u64>, max_files: u32) -> io::Result<RotatingFileWriter> {
+        let file = OpenOptions::new().create(true).write(true).truncate(true).open(path)?;
+
+        Ok(RotatingFileWriter {
+            path: String::from(path),
+            file,
+            size: 0,
+            max_size,
+            max_files
+        })
+    }
+
+    /**
+     * Shifts path.1 .. path.N along by one, moves the current file to path.1 and starts a new one.  The oldest
+     * file is dropped once there are max_files of them.  With max_files of 0 the current file is just truncated.
+     **/
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        if self.max_files > 0 {
+            let _ = fs::remove_file(format!("{}.{}", self.path, self.max_files));
+
+            for index in (1..self.max_files).rev() {
+                let _ = fs::rename(format!("{}.{}", self.path, index), format!("{}.{}", self.path, index + 1));
+            }
+
+            fs::rename(&self.path, format!("{}.1", self.path))?;
+        }
+
+        self.file = OpenOptions::new().create(true).write(true).truncate(true).open(&self.path)?;
+        self.size = 0;
+
+        Ok(())
+    }
+
+}
+
+impl Write for RotatingFileWriter {
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+
+        if let Some(max_size) = self.max_size {
+
+            if self.size > 0 && self.size + buf.len() as u64 > max_size {
+                self.rotate()?;
+            }
+
+        }
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+
+}
+
+impl SharedWriter {
+
+    pub fn new(writer: RotatingFileWriter) -> SharedWriter {
+        SharedWriter { inner: Arc::new(Mutex::new(writer)) }
+    }
+
+}
+
+impl Write for SharedWriter {
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Write the whole chunk while holding the lock so stdout and stderr don't interleave mid-chunk.
+        let mut writer = self.inner.lock().unwrap();
+        writer.write_all(buf)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.lock().unwrap().flush()
+    }
+
+}
+
+impl TeeWriter {
+
+    pub fn new() -> TeeWriter {
+        TeeWriter { writers: vec![] }
+    }
+
+    pub fn add(&mut self, writer: Box<Write + Send>) {
+        self.writers.push(writer);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writers.is_empty()
+    }
+
+}
+
+impl Write for TeeWriter {
+
+    /**
+     * Writes to every writer even if one fails, eg. stdout being closed by `| head` mustn't stop the copy being
+     * saved to a file.  Writers that fail are dropped and the first error is returned once the others have been
+     * written.
+     **/
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut first_error: Option<io::Error> = None;
+        let mut index = 0;
+
+        while index < self.writers.len() {
+
+            match self.writers[index].write_all(buf) {
+                Ok(_) => index += 1,
+                Err(e) => {
+                    self.writers.remove(index);
+                    first_error = first_error.or(Some(e));
+                }
+            };
+
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(buf.len())
+        }
+
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let mut first_error: Option<io::Error> = None;
+
+        for writer in self.writers.iter_mut() {
+
+            if let Err(e) = writer.flush() {
+                first_error = first_error.or(Some(e));
+            }
+
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(())
+        }
+
+    }
+
+}
+
+impl LinePrefixWriter {
+
+    pub fn new(inner: Box<Write + Send>, fields: &[PrefixField], context: &LineContext, stream: &'static str) -> LinePrefixWriter {
+
+        LinePrefixWriter {
+            inner,
+            fields: fields.to_vec(),
+            task_name: context.task_name.clone(),
+            task_id: context.task_id.clone(),
+            hostname: context.hostname.clone(),
+            stream,
+            buffer: vec![]
+        }
+
+    }
+
+    fn prefix(&self) -> String {
+
+        let parts: Vec<String> = self.fields.iter().map(|field| match *field {
+            PrefixField::Time => Local::now().format("%Y-%m-%dT%H:%M:%S%.3f").to_string(),
+            PrefixField::Name => self.task_name.clone(),
+            PrefixField::Task => self.task_id.clone(),
+            PrefixField::Host => self.hostname.clone(),
+            PrefixField::Stream => String::from(self.stream)
+        }).collect();
+
+        format!("[{}] ", parts.join(" "))
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        let mut prefixed: Vec<u8> = self.prefix().into_bytes();
+
+        prefixed.extend_from_slice(line);
+
+        self.inner.write_all(&prefixed[..])
+    }
+
+}
+
+impl Write for LinePrefixWriter {
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+
+        while let Some(index) = self.buffer.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..index + 1).collect();
+            self.write_line(&line[..])?;
+        }
+
+        if self.buffer.len() > MAX_LINE_BUFFER {
+            let line: Vec<u8> = self.buffer.drain(..).collect();
+            self.write_line(&line[..])?;
+        }
+
+        Ok(buf.len())
+    }
+
+    /**
+     * Writes out any partial line.  Only called once the stream has ended.
+     **/
+    fn flush(&mut self) -> io::Result<()> {
+
+        if !self.buffer.is_empty() {
+            let line: Vec<u8> = self.buffer.drain(..).collect();
+            self.write_line(&line[..])?;
+        }
+
+        self.inner.flush()
+    }
+
+}
+
+fn open_file(path: &str, config: &OutputConfig) -> RotatingFileWriter {
+
+    match RotatingFileWriter::open(path, config.max_size, config.max_files) {
+        Ok(writer) => writer,
+        Err(e) => {
+            error!("Unable to open {} for writing: {}", path, e);
+            ::std::process::exit(1);
+        }
+    }
+
+}
+
+/**
+ * Builds the writers for the task's stdout and stderr.  stderr is None when it is neither shown nor saved.
+ **/
+pub fn create_writers(config: &OutputConfig, show_stderr: bool, events: &EventLog, context: &LineContext) -> (Box<Write + Send>, Option<Box<Write + Send>>) {
+    let log = match config.log_file {
+        Some(ref path) => Some(SharedWriter::new(open_file(path.as_str(), config))),
+        None => None
+    };
+
+    let mut stdout_writer = TeeWriter::new();
+    let mut stderr_writer = TeeWriter::new();
+
+    stdout_writer.add(Box::new(stdout()));
+
+    if show_stderr {
+        stderr_writer.add(Box::new(stderr()));
+    }
+
+    if let Some(ref path) = config.stdout_file {
+        stdout_writer.add(Box::new(open_file(path.as_str(), config)));
+    }
+
+    if let Some(ref path) = config.stderr_file {
+        stderr_writer.add(Box::new(open_file(path.as_str(), config)));
+    }
+
+    if let Some(ref log) = log {
+        stdout_writer.add(Box::new(log.clone()));
+        stderr_writer.add(Box::new(log.clone()));
+    }
+
+    // Events carry the stream themselves, so only what is displayed or saved is prefixed.
+    let (mut stdout_writer, mut stderr_writer) = match config.prefix.is_empty() {
+        true => (stdout_writer, stderr_writer),
+        false => {
+            let mut prefixed_stdout = TeeWriter::new();
+            let mut prefixed_stderr = TeeWriter::new();
+
+            prefixed_stdout.add(Box::new(LinePrefixWriter::new(Box::new(stdout_writer), &config.prefix[..], context, "out")));
+
+            if !stderr_writer.is_empty() {
+                prefixed_stderr.add(Box::new(LinePrefixWriter::new(Box::new(stderr_writer), &config.prefix[..], context, "err")));
+            }
+
+            (prefixed_stdout, prefixed_stderr)
+        }
+    };
+
+    if events.includes_output() {
+        stdout_writer.add(Box::new(OutputEventWriter::new(events.clone(), "stdout")));
+        stderr_writer.add(Box::new(OutputEventWriter::new(events.clone(), "stderr")));
+    }
+
+    let stderr_writer: Option<Box<Write + Send>> = match stderr_writer.is_empty() {
+        true => None,
+        false => Some(Box::new(stderr_writer))
+    };
+
+    (Box::new(stdout_writer), stderr_writer)
+}
@@ -1,9 +1,18 @@
 use base64::decode;
 use chrono;
+use artifacts;
 use console;
+use events::EventLog;
+use constraints::ConstraintContext;
+use explain::{OfferLog, Rejection};
+use jobs::JobHandle;
+use selector;
+use selector::OfferSelector;
 use hyper::header::{ContentType, Headers};
 use mesos;
 use network;
+use network::RequestError;
+use output;
 use rand::{Rng, thread_rng};
 use regex;
 use reqwest;
@@ -19,12 +28,29 @@ use std::sync::mpsc;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Mutex;
 use std::sync::RwLock;
+use std::sync::mpsc::RecvTimeoutError;
+use std::thread;
+use std::time::{Duration, Instant};
 use timer::{Guard, Timer};
 use types;
+use upload;
+use upload::UploadServer;
 use strum::AsStaticRef;
 
 header! { (MesosStreamId, "Mesos-Stream-Id") => [String] }
 
+pub const FRAMEWORK_NAME: &'static str = "RExe task executor";
+const EXPLAIN_CANDIDATES: usize = 3;
+const TICK_MILLIS: u64 = 100;
+const MAX_REDIRECTS: u32 = 3;
+const RESUBSCRIBE_ATTEMPTS: u32 = 5;
+// How long the master keeps our task around while we find the new leader.
+const FOLLOW_LEADER_FAILOVER_TIMEOUT: f64 = 300.0;
+// How long a --detach framework stays registered without RExe connected.
+const DETACH_FAILOVER_TIMEOUT: f64 = 604800.0;
+// Exit code when the task is abandoned with --on-unhealthy fail.
+const UNHEALTHY_EXIT_CODE: i32 = 30;
+
 #[derive(Debug,PartialEq,AsStaticStr)]
 enum SchedulerState {
     Started,
@@ -37,11 +63,16 @@ enum SchedulerState {
  * In order to get the sandbox path, we need to know the work_dir on the agent we are talking to.
  * so we need to get that from the agent state endpoint.
  **/
-fn get_sandbox_path(agent_state_url: String, agent_id: &str, framework_id: &str, executor_id: &str, container_id: &str) -> String {
+pub fn get_sandbox_path(agent_state_url: String, client: &reqwest::Client, agent_id: &str, framework_id: &str, executor_id: &str, container_id: &str) -> String {
     let url = agent_state_url.as_str();
-    let state = reqwest::get(url).unwrap().text().unwrap();
 
-    let json: Value = serde_json::from_str(state.as_str()).unwrap();
+    let json: Value = match network::get(client, url).and_then(|mut response| network::read_json(&mut response)) {
+        Ok(json) => json,
+        Err(e) => {
+            error!("Unable to get Mesos agent state from {}: {}", url, e);
+            Value::Null
+        }
+    };
 
     let mut path: String = match json["flags"]["work_dir"].as_str() {
         Some(path) => String::from(path),
@@ -66,7 +97,10 @@ fn get_sandbox_path(agent_state_url: String, agent_id: &str, framework_id: &str,
 pub struct Scheduler<'a> {
     console: Option<Box<console::Console>>,
     state: SchedulerState,
-    scheduler_url: &'a str,
+    client: reqwest::Client,
+    agent_client: reqwest::Client,
+    master_url: String,
+    scheduler_url: String,
     task_info: &'a types::RequestedTaskInfo,
     framework_id: String,
     stream_id: String,
@@ -78,17 +112,37 @@ pub struct Scheduler<'a> {
     sandbox_path: Option<String>,
     timeout_timer: Timer,
     timeout_timer_guard: Option<Guard>,
-    timeout_timer_state_tx: Option<Sender<SchedulerState>>
+    timeout_timer_state_tx: Option<Sender<SchedulerState>>,
+    constraint_context: ConstraintContext,
+    offer_log: Arc<Mutex<OfferLog>>,
+    selector: Box<OfferSelector>,
+    candidates: Vec<types::Offer>,
+    offer_window_start: Option<Instant>,
+    launch_attempts: u32,
+    uploads: Option<UploadServer>,
+    events: EventLog,
+    // Agent already holding the --persistent-volume, if it has been created.
+    volume_agent: Option<String>,
+    // Last health reported for the task.  None until the first health check result.
+    healthy: Option<bool>,
+    // Event stream from a leader found while delivering a call, for the event loop to switch to.
+    event_stream: Option<Receiver<String>>
 }
 
 impl<'a, 'b: 'a> Scheduler<'a> {
 
-    fn new(scheduler_url: &'a str, task_info: &'a types::RequestedTaskInfo, stream_id: String) -> Scheduler<'a> {
-        let running_state = Arc::new(RwLock::new(true));
+    fn new(master_url: String, scheduler_url: String, task_info: &'a types::RequestedTaskInfo, stream_id: String, uploads: Option<UploadServer>, events: EventLog) -> Scheduler<'a> {
+        let mut headers = Headers::new();
+
+        headers.set(ContentType::json());
+        headers.set(MesosStreamId(stream_id.clone()));
 
         let new_scheduler = Scheduler {
             console: None,
             state: SchedulerState::Started,
+            client: network::create_client(&task_info.http, headers),
+            agent_client: network::create_json_client(&task_info.http),
+            master_url,
             scheduler_url,
             task_info,
             framework_id: String::from(""),
@@ -101,18 +155,77 @@ impl<'a, 'b: 'a> Scheduler<'a> {
             sandbox_path: None,
             timeout_timer: Timer::new(),
             timeout_timer_guard: None,
-            timeout_timer_state_tx: None
+            timeout_timer_state_tx: None,
+            constraint_context: ConstraintContext::empty(),
+            offer_log: Arc::new(Mutex::new(OfferLog::new())),
+            selector: selector::create_selector(task_info.offer_strategy),
+            candidates: vec![],
+            offer_window_start: None,
+            launch_attempts: 0,
+            uploads,
+            events,
+            volume_agent: None,
+            healthy: None,
+            event_stream: None
         };
 
         new_scheduler
     }
 
     fn start(&mut self) {
+        let needs_context = self.task_info.constraints.needs_context() ||
+            self.task_info.prefer.needs_context() ||
+            self.task_info.avoid.needs_context();
+
+        if needs_context {
+
+            match ConstraintContext::fetch(self.master_url.as_str(), &self.task_info.http, FRAMEWORK_NAME) {
+                Ok(context) => self.constraint_context = context,
+                Err(e) => {
+                    error!("Unable to evaluate GROUP_BY/MAX_PER constraints: {}", e);
+                    result_exit(&self.events, 1);
+                }
+            };
+
+        }
+
+        if let (Some(volume), Some(role)) = (self.task_info.persistent_volume.as_ref(), self.task_info.role.as_ref()) {
+
+            match find_volume_agent(self.master_url.as_str(), &self.task_info.http, role.as_str(), volume.id.as_str()) {
+                Ok(Some((agent_id, hostname))) => {
+
+                    if self.task_info.verbose_output {
+                        println!("Persistent volume {} is on {}", volume.id, hostname);
+                    }
+
+                    self.volume_agent = Some(agent_id);
+                },
+                Ok(None) => {
+
+                    if self.task_info.verbose_output {
+                        println!("Persistent volume {} doesn't exist yet and will be created", volume.id);
+                    }
+
+                },
+                Err(e) => {
+                    error!("Unable to find persistent volume {}: {}", volume.id, e);
+                    result_exit(&self.events, 1);
+                }
+            };
+
+        }
+
+        self.start_timeout_timer();
+    }
+
+    fn start_timeout_timer(&mut self) {
         let (tx, rx): (Sender<SchedulerState>, Receiver<SchedulerState>) = mpsc::channel();
 
         self.timeout_timer_state_tx = Some(tx);
 
         if (self.task_info.timeout > 0) {
+            let offer_log = Arc::clone(&self.offer_log);
+            let events = self.events.clone();
 
             self.timeout_timer_guard = Some(self.timeout_timer.schedule_with_delay(chrono::Duration::seconds(self.task_info.timeout), move || {
 
@@ -120,13 +233,14 @@ impl<'a, 'b: 'a> Scheduler<'a> {
                     Err(e) => {
                         // Exit.
                         error!("Timeout waiting for acceptable resource offer from Mesos");
-                        exit(10);
+                        eprint!("{}", offer_log.lock().unwrap().summary(EXPLAIN_CANDIDATES));
+                        result_exit(&events, 10);
                     },
                     Ok(state) => {
 
                         if state != SchedulerState::Scheduled {
                             error!("Unexpected Scheduler state: {}", state.as_static());
-                            exit(20);
+                            result_exit(&events, 20);
                         }
                     }
 
@@ -149,10 +263,31 @@ impl<'a, 'b: 'a> Scheduler<'a> {
     }
 
     fn set_subscribed(&mut self, framework_id: String) {
-        self.state = SchedulerState::Subscribed;
+
+        // A re-subscription after a master failover must not reset the progress of the task.
+        if self.state == SchedulerState::Started {
+            self.state = SchedulerState::Subscribed;
+        }
+
         self.framework_id = framework_id;
     }
 
+    fn is_subscribed(&self) -> bool {
+        self.state != SchedulerState::Started
+    }
+
+    fn resubscribe(&mut self, master_url: String, scheduler_url: String, stream_id: String) {
+        let mut headers = Headers::new();
+
+        headers.set(ContentType::json());
+        headers.set(MesosStreamId(stream_id.clone()));
+
+        self.client = network::create_client(&self.task_info.http, headers);
+        self.master_url = master_url;
+        self.scheduler_url = scheduler_url;
+        self.stream_id = stream_id;
+    }
+
     fn handle_message(&mut self, message: String) {
         let value: Value = serde_json::from_str(message.as_str()).unwrap();
         let message_type = value["type"].as_str().unwrap();
@@ -163,6 +298,11 @@ impl<'a, 'b: 'a> Scheduler<'a> {
                 let framework_id = value["subscribed"]["framework_id"]["value"].as_str().unwrap();
                 self.set_subscribed(String::from(framework_id));
 
+                self.events.emit("subscribed", json!({
+                    "framework_id": framework_id,
+                    "master": self.master_url
+                }));
+
                 if self.task_info.verbose_output {
                     println!("Subscribed to Mesos with framework_id: {}", self.framework_id);
                 }
@@ -176,6 +316,7 @@ impl<'a, 'b: 'a> Scheduler<'a> {
 
                 match value["offers"]["offers"].as_array() {
                     Some(offers) => {
+                        let mut declined: Vec<String> = vec![];
 
                         for offer_value in offers {
                             let offer = types::Offer::from(offer_value);
@@ -183,35 +324,76 @@ impl<'a, 'b: 'a> Scheduler<'a> {
                             if !self.is_scheduled() {
 
                                 if self.is_useable_offer(&offer) {
-                                    self.accept_offer(&offer);
+                                    self.add_candidate(offer);
                                     continue;
                                 }
 
                             }
 
-                            let scheduled = self.is_scheduled();
-
-                            self.decline_offer(&offer, match scheduled {
-                                true => 600f32,
-                                false => 5f32
-                            });
-
+                            declined.push(offer.offer_id.clone());
                         }
 
+                        self.decline_offers(declined);
                     },
 
                     None => println!("Didn't find any offers")
                 }
 
+                self.select_candidate();
+            },
+            "RESCIND" => {
+
+                match value["rescind"]["offer_id"]["value"].as_str() {
+                    Some(offer_id) => {
+                        self.candidates.retain(|offer| offer.offer_id != offer_id);
+
+                        if self.candidates.is_empty() {
+                            self.offer_window_start = None;
+                        }
+
+                    },
+                    None => {}
+                };
+
             },
             "HEARTBEAT" => {
                 // Cool story, bro.
             },
             "UPDATE" => {
+                let status = &value["update"]["status"];
+
+                self.events.emit("task_state", json!({
+                    "state": status["state"],
+                    "reason": status["reason"],
+                    "message": status["message"],
+                    "source": status["source"],
+                    "task_id": status["task_id"]["value"],
+                    "agent_id": status["agent_id"]["value"],
+                    "hostname": self.agent_hostname
+                }));
 
                 match value["update"]["status"]["state"].as_str() {
                     Some(state) => match state {
 
+                        "TASK_DROPPED" |
+                        "TASK_LOST" |
+                        "TASK_GONE" if self.state == SchedulerState::Scheduled => {
+
+                            if let Some(uuid) = value["update"]["status"]["uuid"].as_str() {
+                                self.acknowledge(uuid);
+                            }
+
+                            if !self.relaunch(state) {
+
+                                error!("{}\n{}",
+                                        value["update"]["status"]["reason"],
+                                        value["update"]["status"]["message"],
+                                );
+
+                                self.deregister_exit(1);
+                            }
+
+                        },
                         "TASK_ERROR" |
                         "TASK_FAILED" |
                         "TASK_KILLED" |
@@ -225,6 +407,12 @@ impl<'a, 'b: 'a> Scheduler<'a> {
                                     value["update"]["status"]["message"],
                             );
 
+                            match self.console {
+                                Some(ref mut console) => console.finish(),
+                                None => {}
+                            };
+
+                            self.collect_artifacts(&value);
                             self.deregister_exit(1);
                         },
                         "TASK_RUNNING" => {
@@ -259,47 +447,63 @@ impl<'a, 'b: 'a> Scheduler<'a> {
 
                             };
 
-                            let executor_id = value["update"]["status"]["executor_id"]["value"].as_str().unwrap();
-                            let container_id = value["update"]["status"]["container_status"]["container_id"]["value"].as_str().unwrap();
+                            // Health check results arrive as further TASK_RUNNING updates.
+                            if !set_running {
+                                self.report_health(&value["update"]["status"]);
+                            }
 
                             if set_running {
                                 self.state = SchedulerState::Running;
 
-                                let mut agent_url: String = match self.agent_scheme {
-                                    Some(ref url) => String::from(url.as_str()),
-                                    None => String::from("")
+                                let mut api_url = self.agent_url();
+
+                                api_url.push_str("/api/v1");
+
+                                let sandbox_path = match self.task_info.tty_mode {
+                                    types::TTYMode::Headless if !self.task_info.detach => self.resolve_sandbox_path(&value),
+                                    types::TTYMode::Interactive if !self.task_info.collect.is_empty() => self.resolve_sandbox_path(&value),
+                                    _ => None
                                 };
 
-                                agent_url.push_str("://");
-                                agent_url.push_str(self.agent_hostname.as_ref().unwrap().as_str());
-                                agent_url.push_str(":");
-                                agent_url.push_str(&self.agent_port.unwrap().to_string());
+                                // The fetcher has run by now.  Checked before detaching so the upload server is still up.
+                                let verified = match self.uploads {
+                                    Some(ref uploads) => uploads.verify(),
+                                    None => Ok(())
+                                };
 
-                                let mut api_url = agent_url.clone();
-                                let mut agent_state_url = agent_url.clone();
+                                if let Err(e) = verified {
+                                    error!("{}", e);
+                                    self.deregister_exit(1);
+                                }
 
-                                api_url.push_str("/api/v1");
-                                agent_state_url.push_str("/state");
+                                if self.task_info.detach {
+                                    self.detach_exit();
+                                }
+
+                                let context = output::LineContext {
+                                    task_name: self.task_info.task_name.clone(),
+                                    task_id: self.task_id.clone().unwrap_or_default(),
+                                    hostname: self.agent_hostname.clone().unwrap_or_default()
+                                };
 
                                 let mut console: Box<console::Console> = match self.task_info.tty_mode {
                                     types::TTYMode::Headless => Box::new(
                                         console::HeadlessConsole::new(
                                             api_url.as_str(),
-                                            get_sandbox_path(
-                                                agent_state_url,
-                                                self.agent_id.as_ref().unwrap().as_str(),
-                                                self.framework_id.as_str(),
-                                                executor_id,
-                                                container_id
-                                            ).as_str(),
-                                            self.task_info.stderr
+                                            sandbox_path.as_ref().unwrap().as_str(),
+                                            value["update"]["status"]["container_status"]["container_id"]["value"].as_str().unwrap(),
+                                            &self.task_info,
+                                            &self.events,
+                                            &context
                                         )
                                     ),
                                     types::TTYMode::Interactive => Box::new(
                                         console::InteractiveConsole::new(
                                             api_url.as_str(),
                                             value["update"]["status"]["container_status"]["container_id"]["value"].as_str().unwrap(),
-                                            self.task_info.stderr
+                                            &self.task_info,
+                                            &self.events,
+                                            &context
                                         )
                                     )
                                 };
@@ -340,6 +544,7 @@ impl<'a, 'b: 'a> Scheduler<'a> {
 
                             }
 
+                            self.collect_artifacts(&value);
                             self.deregister_exit(0);
                         },
                         "TASK_STARTING" => {
@@ -373,15 +578,266 @@ impl<'a, 'b: 'a> Scheduler<'a> {
 
     }
 
-    fn deregister_exit(&self, exit_code: i32) {
+    fn agent_url(&self) -> String {
+        let mut agent_url: String = match self.agent_scheme {
+            Some(ref url) => String::from(url.as_str()),
+            None => String::from("")
+        };
+
+        agent_url.push_str("://");
+        agent_url.push_str(self.agent_hostname.as_ref().unwrap().as_str());
+        agent_url.push_str(":");
+        agent_url.push_str(&self.agent_port.unwrap().to_string());
+
+        agent_url
+    }
+
+    /**
+     * Works out the task's sandbox from the first status update carrying its executor and container ids.
+     **/
+    fn resolve_sandbox_path(&mut self, value: &Value) -> Option<String> {
+
+        if self.sandbox_path.is_none() {
+            let executor_id = value["update"]["status"]["executor_id"]["value"].as_str();
+            let container_id = value["update"]["status"]["container_status"]["container_id"]["value"].as_str();
+
+            if let (Some(executor_id), Some(container_id), Some(agent_id)) = (executor_id, container_id, self.agent_id.as_ref()) {
+                let mut agent_state_url = self.agent_url();
+
+                agent_state_url.push_str("/state");
+
+                self.sandbox_path = Some(get_sandbox_path(
+                    agent_state_url,
+                    &self.agent_client,
+                    agent_id.as_str(),
+                    self.framework_id.as_str(),
+                    executor_id,
+                    container_id
+                ));
+            }
+
+        }
+
+        self.sandbox_path.clone()
+    }
+
+    fn collect_artifacts(&mut self, value: &Value) {
+
+        if self.task_info.collect.is_empty() || self.agent_hostname.is_none() {
+            return;
+        }
+
+        match self.resolve_sandbox_path(value) {
+
+            Some(sandbox_path) => {
+                let mut api_url = self.agent_url();
+
+                api_url.push_str("/api/v1");
+
+                let collected = artifacts::collect(
+                    api_url.as_str(),
+                    &self.agent_client,
+                    sandbox_path.as_str(),
+                    &self.task_info.collect[..],
+                    self.task_info.collect_dir.as_str(),
+                    self.task_info.verbose_output
+                );
+
+                if self.task_info.verbose_output {
+                    println!("Collected {} files into {}", collected, self.task_info.collect_dir);
+                }
+
+            },
+            None => error!("Unable to collect artifacts.  The task's sandbox is unknown")
+        };
+
+    }
+
+    fn add_candidate(&mut self, offer: types::Offer) {
+
+        if self.offer_window_start.is_none() {
+            self.offer_window_start = Some(Instant::now());
+        }
+
+        self.candidates.push(offer);
+    }
+
+    /**
+     * Once the offer window has elapsed, launch on the candidate chosen by the selector and decline the rest.
+     **/
+    fn select_candidate(&mut self) {
+
+        if self.is_scheduled() || self.candidates.is_empty() {
+            return;
+        }
+
+        match self.offer_window_start {
+            Some(started) if started.elapsed() < Duration::from_millis(self.task_info.offer_window) => return,
+            _ => {}
+        };
+
+        let candidates: Vec<types::Offer> = self.candidates.drain(..).collect();
+        self.offer_window_start = None;
+
+        let selected = self.selector.select(self.task_info, &self.constraint_context, &candidates[..]);
+
+        if let Some(index) = selected {
+
+            if self.task_info.verbose_output {
+                println!("Selected offer from {} out of {} candidates", candidates[index].hostname, candidates.len());
+            }
+
+            self.accept_offer(&candidates[index]);
+        }
+
+        let declined: Vec<String> = candidates.iter()
+            .enumerate()
+            .filter(|&(index, _)| Some(index) != selected)
+            .map(|(_, offer)| offer.offer_id.clone())
+            .collect();
+
+        self.decline_offers(declined);
+    }
+
+    /**
+     * A task that was dropped or lost before it started can safely be launched again.  Returns false if
+     * the task isn't eligible for a relaunch.
+     **/
+    fn relaunch(&mut self, state: &str) -> bool {
+
+        if self.state != SchedulerState::Scheduled || self.launch_attempts >= self.task_info.max_launch_attempts {
+            return false;
+        }
+
+        println!(
+            "Task reported {} before it started.  Relaunching (attempt {} of {})",
+            state,
+            self.launch_attempts + 1,
+            self.task_info.max_launch_attempts
+        );
+
+        self.state = SchedulerState::Subscribed;
+        self.task_id = None;
+        self.agent_id = None;
+        self.agent_scheme = None;
+        self.agent_hostname = None;
+        self.agent_port = None;
+        self.healthy = None;
+
+        self.revive();
+        self.start_timeout_timer();
+
+        true
+    }
+
+    /**
+     * Reports the task becoming healthy or unhealthy and acts on --on-unhealthy.  Repeated updates with the
+     * same health are ignored.
+     **/
+    fn report_health(&mut self, status: &Value) {
+
+        let healthy = match status["healthy"].as_bool() {
+            Some(healthy) => healthy,
+            None => return
+        };
+
+        if self.healthy == Some(healthy) {
+            return;
+        }
+
+        self.healthy = Some(healthy);
+
+        self.events.emit("health", json!({
+            "healthy": healthy,
+            "task_id": self.task_id,
+            "hostname": self.agent_hostname,
+            "message": status["message"]
+        }));
+
+        let task_id = self.task_id.clone().unwrap_or_default();
+
+        if healthy {
+            eprintln!("Task {} is healthy", task_id);
+            return;
+        }
+
+        eprintln!("Task {} is unhealthy", task_id);
+
+        match self.task_info.health_check.as_ref().map(|health_check| health_check.on_unhealthy) {
+            Some(types::UnhealthyAction::Kill) => self.kill(),
+            Some(types::UnhealthyAction::Fail) => {
+                error!("Task {} failed its health check", task_id);
+                self.deregister_exit(UNHEALTHY_EXIT_CODE);
+            },
+            _ => {}
+        };
+
+    }
+
+    fn kill(&mut self) {
+
+        let body_content = match (self.agent_id.as_ref(), self.task_id.as_ref()) {
+            (Some(agent_id), Some(task_id)) => serde_json::to_string(&types::kill_request(&self.framework_id, agent_id, task_id)).unwrap(),
+            _ => return
+        };
+
+        if let Err(e) = self.deliver_request(body_content) {
+            println!("Error sending kill to master: {}", e);
+        }
+
+    }
+
+    /**
+     * Leaves the task running and exits without tearing down the framework, printing the handle used by the
+     * attach, logs, wait and kill commands.
+     **/
+    fn detach_exit(&mut self) {
+
+        if let Some(ref mut uploads) = self.uploads {
+            uploads.stop();
+        }
+
+        let handle = JobHandle {
+            framework_id: self.framework_id.clone(),
+            task_id: self.task_id.clone().unwrap_or_default(),
+            agent_id: self.agent_id.clone().unwrap_or_default()
+        };
+
+        self.events.emit("detached", json!({
+            "handle": handle.to_string(),
+            "framework_id": handle.framework_id,
+            "task_id": handle.task_id,
+            "agent_id": handle.agent_id,
+            "hostname": self.agent_hostname
+        }));
+
+        if self.task_info.verbose_output {
+            println!("Task is running on {}.  Detaching", self.agent_hostname.clone().unwrap_or_default());
+        }
+
+        println!("{}", handle);
+
+        self.events.result(0);
+
+        let _ = stdout().flush();
+
+        exit(0);
+    }
+
+    fn deregister_exit(&mut self, exit_code: i32) {
+
+        if let Some(ref mut uploads) = self.uploads {
+            uploads.stop();
+        }
+
         let request = types::teardown_request(&self.framework_id);
         let body_content = serde_json::to_string(&request).unwrap();
 
-        if !self.deliver_request(body_content) {
-            println!("Unable to send teardown call to master. Exiting anyway.");
+        if let Err(e) = self.deliver_request(body_content) {
+            println!("Unable to send teardown call to master: {}. Exiting anyway.", e);
         }
 
-        exit(exit_code);
+        result_exit(&self.events, exit_code);
     }
 
     fn acknowledge(&mut self, uuid: &str) {
@@ -392,8 +848,8 @@ impl<'a, 'b: 'a> Scheduler<'a> {
 
         let body_content = serde_json::to_string(&request).unwrap();
 
-        if !self.deliver_request(body_content) {
-            println!("Problem with sending acknowledge message to the server.");
+        if let Err(e) = self.deliver_request(body_content) {
+            println!("Problem with sending acknowledge message to the server: {}", e);
         }
 
     }
@@ -401,15 +857,35 @@ impl<'a, 'b: 'a> Scheduler<'a> {
     fn accept_offer(&mut self, offer: &types::Offer) {
         let task_id: String = thread_rng().gen_ascii_chars().take(10).collect();
         
+        let uris = match self.uploads {
+            Some(ref uploads) => uploads.uris(),
+            None => vec![]
+        };
+
         let request = types::accept_request(
             &self.framework_id,
             &offer.offer_id,
             &offer.agent_id,
             &task_id,
             &self.task_info,
-            &self.task_info.tty_mode
+            &self.task_info.tty_mode,
+            uris,
+            self.volume_placement(offer)
         );
 
+        if self.task_info.dry_run {
+
+            if self.task_info.explain {
+                eprint!("{}", self.offer_log.lock().unwrap().summary(EXPLAIN_CANDIDATES));
+                eprintln!("Would accept offer from {}", offer.hostname);
+            }
+
+            println!("{}", serde_json::to_string_pretty(&request).unwrap());
+
+            self.decline_offers(vec![offer.offer_id.clone()]);
+            self.deregister_exit(0);
+        }
+
         let body_content = serde_json::to_string(&request).unwrap();
 
         if self.task_info.verbose_output {
@@ -418,7 +894,15 @@ impl<'a, 'b: 'a> Scheduler<'a> {
 
         let output = body_content.clone();
 
-        if self.deliver_request(body_content) {
+        if self.task_info.explain {
+            eprint!("{}", self.offer_log.lock().unwrap().summary(EXPLAIN_CANDIDATES));
+            eprintln!("Accepted offer from {}", offer.hostname);
+        }
+
+        let delivery = self.deliver_request(body_content);
+
+        if delivery.is_ok() {
+            self.launch_attempts += 1;
             self.state = SchedulerState::Scheduled;
             self.task_id = Some(task_id.clone());
             self.agent_id = Some(offer.agent_id.clone());
@@ -426,125 +910,461 @@ impl<'a, 'b: 'a> Scheduler<'a> {
             self.agent_hostname = Some(offer.hostname.clone());
             self.agent_port = Some(offer.port.clone());
 
+            if self.task_info.persistent_volume.is_some() {
+                // A relaunch has to go back to the agent the volume was just created on.
+                self.volume_agent = Some(offer.agent_id.clone());
+            }
+
             self.timeout_timer_state_tx.as_ref().unwrap().send(SchedulerState::Scheduled);
 
+            self.events.emit("offer_accepted", json!({
+                "offer_id": offer.offer_id,
+                "agent_id": offer.agent_id,
+                "hostname": offer.hostname,
+                "task_id": task_id,
+                "attempt": self.launch_attempts,
+                "resources": {
+                    "cpus": self.task_info.cpus,
+                    "mem": self.task_info.mem,
+                    "disk": self.task_info.disk,
+                    "gpus": self.task_info.gpus
+                },
+                "offered": {
+                    "cpus": offer.cpus,
+                    "mem": offer.mem,
+                    "disk": offer.disk,
+                    "gpus": offer.gpus
+                }
+            }));
+
+            // We only ever run a single task so there is no point in the master sending us any more offers.
+            self.suppress();
+
         } else {
-            error!("Error sending acceptance offer to mesos\n\n{}", output);
+            error!("Error sending acceptance offer to mesos: {}\n\n{}", delivery.err().unwrap(), output);
             self.deregister_exit(1);
         }
 
     }
 
-    fn decline_offer(&mut self, offer: &types::Offer, refuse_seconds: f32) {
+    fn decline_offers(&mut self, offer_ids: Vec<String>) {
+
+        if offer_ids.is_empty() {
+            return;
+        }
 
         let request = types::decline_request(
             &self.framework_id,
-            &offer.offer_id,
-            match (&self.state) {
-                &SchedulerState::Running => true,
-                _ => false
-            },
-            refuse_seconds
+            &offer_ids[..],
+            match self.is_scheduled() {
+                true => 600f32,
+                false => 5f32
+            }
         );
 
         let body_content = serde_json::to_string(&request).unwrap();
 
-        if !self.deliver_request(body_content) {
-            println!("Error sending decline to master");
+        if let Err(e) = self.deliver_request(body_content) {
+            println!("Error sending decline to master: {}", e);
+        }
+
+    }
+
+    fn suppress(&mut self) {
+        let request = types::suppress_request(&self.framework_id);
+        let body_content = serde_json::to_string(&request).unwrap();
+
+        if let Err(e) = self.deliver_request(body_content) {
+            println!("Error sending suppress to master: {}", e);
+        }
+
+    }
+
+    fn revive(&mut self) {
+        let request = types::revive_request(&self.framework_id);
+        let body_content = serde_json::to_string(&request).unwrap();
+
+        if let Err(e) = self.deliver_request(body_content) {
+            println!("Error sending revive to master: {}", e);
+        }
+
+    }
+
+    /**
+     * Whether the offer already holds the task's persistent volume or it needs to be created.  None without
+     * --persistent-volume.
+     **/
+    fn volume_placement(&self, offer: &types::Offer) -> Option<types::VolumePlacement> {
+
+        match self.task_info.persistent_volume {
+            Some(ref volume) => match offer.persistent_volumes.iter().find(|offered| offered.id == volume.id) {
+                Some(offered) => Some(types::VolumePlacement::Existing(offered.clone())),
+                None => Some(types::VolumePlacement::Create)
+            },
+            None => None
         }
 
     }
 
-    fn is_useable_attribute(&self, attr_name: &String, attr_value: &String, offer: &types::Offer) -> bool {
+    fn get_offer_rejections(&self, offer: &types::Offer) -> Vec<Rejection> {
+        let mut rejections: Vec<Rejection> = vec![];
 
-        for (name, value) in &offer.attributes {
+        if let Err(reason) = self.task_info.constraints.check(&offer.attributes, &self.constraint_context) {
+            rejections.push(Rejection::constraint(reason));
+        }
 
-            if name.eq(attr_name.as_str()) {
+        if offer.cpus < self.task_info.cpus {
+            rejections.push(Rejection::resource("cpus", offer.cpus, self.task_info.cpus));
+        }
 
-                if attr_value.starts_with("/") {
-                    let pattern = attr_value.trim_matches('/');
-                    let attr_regex = regex::Regex::new(pattern).unwrap();
+        if offer.mem < self.task_info.mem {
+            rejections.push(Rejection::resource("mem", offer.mem, self.task_info.mem));
+        }
 
-                    return attr_regex.is_match(value.as_str());
+        match (self.task_info.persistent_volume.as_ref(), self.volume_placement(offer)) {
+            (Some(_), Some(types::VolumePlacement::Existing(_))) |
+            (None, _) => {
 
-                } else {
-                    return value.eq(attr_value.as_str());
+                if offer.disk < self.task_info.disk {
+                    rejections.push(Rejection::resource("disk", offer.disk, self.task_info.disk));
                 }
 
+            },
+            (Some(volume), _) => {
+
+                match self.volume_agent {
+                    Some(ref agent_id) if *agent_id == offer.agent_id => {
+                        rejections.push(Rejection::constraint(format!("persistent volume {} not offered", volume.id)));
+                    },
+                    Some(_) => {
+                        rejections.push(Rejection::constraint(format!("persistent volume {} is on another agent", volume.id)));
+                    },
+                    None if offer.unreserved_disk < self.task_info.disk + volume.size => {
+                        rejections.push(Rejection::resource("disk", offer.unreserved_disk, self.task_info.disk + volume.size));
+                    },
+                    None => {}
+                };
+
             }
+        };
+
+        if offer.gpus < self.task_info.gpus {
+            rejections.push(Rejection::resource("gpus", offer.gpus as f32, self.task_info.gpus as f32));
+        }
+
+        let missing_ports: Vec<String> = self.task_info.network.host_ports().iter()
+            .filter(|&&port| !offer.ports.iter().any(|&(begin, end)| port >= begin && port <= end))
+            .map(|port| port.to_string())
+            .collect();
 
+        if !missing_ports.is_empty() {
+            rejections.push(Rejection::constraint(format!("ports not offered: {}", missing_ports.join(", "))));
         }
 
-        return false;
+        rejections
     }
 
     fn is_useable_offer(&self, offer: &types::Offer) -> bool {
-        // Real basic check for now.  Does the offer have enough resources for us?
-        for (key, value) in self.task_info.attrs.iter() {
+        let rejections = self.get_offer_rejections(offer);
+        let useable = rejections.is_empty();
 
-            if !self.is_useable_attribute(key, value, offer) {
-                return false;
-            }
+        if self.task_info.verbose_output && !useable {
+            let reasons: Vec<&str> = rejections.iter().map(|rejection| rejection.reason.as_str()).collect();
+            println!("Agent {} rejected: {}", offer.hostname, reasons.join("; "));
+        }
+
+        self.offer_log.lock().unwrap().record(offer, rejections);
 
+        useable
+    }
+
+    fn deliver_request(&mut self, body: String) -> Result<reqwest::Response, RequestError> {
+
+        match network::post(&self.client, self.scheduler_url.as_str(), body.clone()) {
+            Err(RequestError::Redirect(location)) => {
+                // Leadership has moved.  The new leader doesn't know our stream id, so subscribe to it again as the
+                // same framework and retry the call on the new stream.
+                let mut master_url = network::base_url(network::resolve_location(self.scheduler_url.as_str(), location.as_str()).as_str());
+
+                if self.task_info.verbose_output {
+                    println!("Redirected to leading master {}", master_url);
+                }
+
+                let mut headers = Headers::new();
+
+                headers.set(ContentType::json());
+
+                let stream_client = network::create_streaming_client(&self.task_info.http, headers);
+                let body_content = subscribe_body(self.task_info, Some(self.framework_id.as_str()));
+                let (scheduler_url, stream_id, response) = subscribe(&stream_client, &mut master_url, body_content, self.task_info.verbose_output)?;
+
+                self.resubscribe(master_url, scheduler_url, stream_id);
+                self.event_stream = Some(read_events(response));
+
+                network::post(&self.client, self.scheduler_url.as_str(), body)
+            },
+            result => result
         }
 
-        offer.cpus >= self.task_info.cpus &&
-            offer.gpus >= self.task_info.gpus &&
-            offer.disk >= self.task_info.disk &&
-            offer.mem >= self.task_info.mem
     }
 
-    fn deliver_request(&self, body: String) -> bool {
-        let mut scheduler_url: String = String::from(self.scheduler_url);
-        scheduler_url.push_str("/api/v1/scheduler");
+}
 
-        let mut headers = Headers::new();
+/**
+ * Reports the result to event consumers and exits.  Exits that can still reach the master go through
+ * deregister_exit so the framework is torn down first.
+ **/
+fn result_exit(events: &EventLog, exit_code: i32) -> ! {
+    events.result(exit_code);
 
-        headers.set(ContentType::json());
-        headers.set(MesosStreamId(String::from(&*self.stream_id)));
+    // exit() doesn't run destructors, so anything still buffered would be lost.
+    let _ = stdout().flush();
+
+    exit(exit_code);
+}
 
-        let client: reqwest::Client = reqwest::ClientBuilder::new()
-            .default_headers(headers)
-            .build().unwrap();
+/**
+ * Looks through the agents' reservations for the role for a persistent volume with the id.  Returns the id and
+ * hostname of the agent holding it.
+ **/
+fn find_volume_agent(master_url: &str, http: &types::HttpConfig, role: &str, persistence_id: &str) -> Result<Option<(String, String)>, String> {
+    let mut slaves_url = String::from(master_url);
+    slaves_url.push_str("/slaves");
+
+    let client = network::create_json_client(http);
+
+    let slaves: Value = match network::get(&client, slaves_url.as_str()).and_then(|mut response| network::read_json(&mut response)) {
+        Ok(slaves) => slaves,
+        Err(e) => return Err(format!("Unable to get agents from {}: {}", slaves_url, e))
+    };
+
+    if let Some(agents) = slaves["slaves"].as_array() {
+
+        for agent in agents {
 
-        match client.post(scheduler_url.as_str())
-            .body(body)
-            .send() {
+            if let Some(resources) = agent["reserved_resources_full"][role].as_array() {
+
+                let found = resources.iter().any(|resource| resource["disk"]["persistence"]["id"].as_str() == Some(persistence_id));
+
+                if found {
+                    return Ok(Some((
+                        String::from(agent["id"].as_str().unwrap_or("")),
+                        String::from(agent["hostname"].as_str().unwrap_or(""))
+                    )));
+                }
 
-            Ok(_) => true,
-            Err(error) => {
-                error!("{}", error);
-                false
             }
 
         }
 
     }
 
+    Ok(None)
 }
 
-pub fn execute<'a>(mesos_host: &'a str, task_info: &'a types::RequestedTaskInfo) {
-    let mut scheduler_uri: String = mesos::discover_mesos_leader(mesos_host);
-    scheduler_uri.push_str("/api/v1/scheduler");
+fn subscribe_body(task_info: &types::RequestedTaskInfo, framework_id: Option<&str>) -> String {
 
-    let url : &str = &scheduler_uri[..];
+    let failover_timeout = match (task_info.detach, task_info.discovery.follow_leader) {
+        (true, _) => Some(DETACH_FAILOVER_TIMEOUT),
+        (false, true) => Some(FOLLOW_LEADER_FAILOVER_TIMEOUT),
+        (false, false) => None
+    };
 
-    if task_info.verbose_output {
-        println!("Sending request to: {}", url)
-    }
+    let checkpoint = match task_info.detach {
+        true => Some(true),
+        false => None
+    };
+
+    framework_subscribe_body(
+        framework_id,
+        task_info.role.as_ref().map(|role| role.as_str()),
+        task_info.principal.as_ref().map(|principal| principal.as_str()),
+        failover_timeout,
+        checkpoint
+    )
+}
+
+/**
+ * The SUBSCRIBE call for the rexe framework.  Used directly by the commands that don't launch a task.
+ **/
+fn framework_subscribe_body(framework_id: Option<&str>, role: Option<&str>, principal: Option<&str>, failover_timeout: Option<f64>, checkpoint: Option<bool>) -> String {
 
     let subscribe_request = types::SubscribeRequest {
         message_type: String::from("SUBSCRIBE"),
+        framework_id: framework_id.map(|id| types::FrameworkID { value: String::from(id) }),
         subscribe: types::SubscribeType {
             framework_info: types::FrameworkInfo {
                 user: String::from("root"),
-                name: String::from("RExe task executor"),
+                name: String::from(FRAMEWORK_NAME),
                 capabilities: vec![],
+                id: framework_id.map(|id| types::FrameworkID { value: String::from(id) }),
+                failover_timeout,
+                checkpoint,
+                role: role.map(String::from),
+                principal: principal.map(String::from)
             }
         }
     };
 
-    let body_content = serde_json::to_string(&subscribe_request).unwrap();
+    serde_json::to_string(&subscribe_request).unwrap()
+}
+
+/**
+ * Sends the SUBSCRIBE call, following redirects to the leading master.  master_uri is updated to the master
+ * that accepted the subscription.
+ **/
+fn subscribe(client: &reqwest::Client, master_uri: &mut String, body_content: String, verbose_output: bool) -> Result<(String, String, reqwest::Response), RequestError> {
+    let mut redirects = 0;
+
+    loop {
+        let mut scheduler_uri = master_uri.clone();
+        scheduler_uri.push_str("/api/v1/scheduler");
+
+        if verbose_output {
+            println!("Sending request to: {}", scheduler_uri)
+        }
+
+        match network::post(client, scheduler_uri.as_str(), body_content.clone()) {
+            Err(RequestError::Redirect(ref location)) if redirects < MAX_REDIRECTS => {
+                redirects += 1;
+                *master_uri = network::base_url(network::resolve_location(scheduler_uri.as_str(), location.as_str()).as_str());
+
+                if verbose_output {
+                    println!("Redirected to leading master {}", master_uri);
+                }
+
+            },
+            Ok(response) => {
+
+                let stream_id = match network::get_header_string_value("Mesos-Stream-Id", response.headers()) {
+                    Some(id) => id,
+                    None => String::from("")
+                };
+
+                if stream_id.is_empty() {
+                    return Err(RequestError::Decode(String::from("Received empty stream id from Mesos")));
+                }
+
+                if verbose_output {
+                    println!("Stream id {}", stream_id);
+                }
+
+                return Ok((scheduler_uri, stream_id, response));
+            },
+            Err(e) => return Err(e)
+        };
+
+    }
+
+}
+
+/**
+ * Events are read on their own thread so we can still act on the offer window while the master is quiet.
+ * The channel disconnects when the event stream ends.
+ **/
+fn read_events(mut response: reqwest::Response) -> Receiver<String> {
+    let (message_tx, message_rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+
+    thread::spawn(move || {
+
+        loop {
+
+            match network::read_next_message(&mut response) {
+                Ok(message) => {
+
+                    if message_tx.send(message).is_err() {
+                        break;
+                    }
+
+                },
+                Err(e) => {
+                    info!("Scheduler event stream closed: {}", e);
+                    break;
+                }
+            };
+
+        }
+
+    });
+
+    message_rx
+}
+
+/**
+ * Re-subscribes with our framework id after losing the event stream, preferring the most recent leader reported
+ * by leader discovery.
+ **/
+fn follow_leader(client: &reqwest::Client, scheduler: &mut Scheduler, leader_rx: &Receiver<String>, master_uri: &mut String) -> Receiver<String> {
+    let task_info = scheduler.task_info;
+
+    for attempt in 0..RESUBSCRIBE_ATTEMPTS {
+        let mut leader: Option<String> = None;
+
+        while let Ok(update) = leader_rx.try_recv() {
+            leader = Some(update);
+        }
+
+        if leader.is_none() {
+            leader = leader_rx.recv_timeout(task_info.discovery.zk_timeout).ok();
+        }
+
+        if let Some(leader) = leader {
+            *master_uri = leader;
+        }
+
+        println!("Lost connection to Mesos master.  Re-subscribing to {}", master_uri);
+
+        let body_content = subscribe_body(task_info, Some(scheduler.framework_id.as_str()));
+
+        match subscribe(client, master_uri, body_content, task_info.verbose_output) {
+            Ok((scheduler_uri, stream_id, response)) => {
+                scheduler.resubscribe(master_uri.clone(), scheduler_uri, stream_id);
+                return read_events(response);
+            },
+            Err(e) => {
+                warn!("Re-subscription attempt {} failed: {}", attempt + 1, e);
+                thread::sleep(Duration::from_secs(1));
+            }
+        };
+
+    }
+
+    error!("Unable to re-subscribe to a Mesos master");
+    result_exit(&scheduler.events, 1);
+}
+
+/**
+ * Prints the ACCEPT call for --offline without contacting a master.  Ids that only the master and the chosen
+ * offer can provide are placeholders, and a persistent volume is rendered as being created.
+ **/
+pub fn render_offline(task_info: &types::RequestedTaskInfo) {
+    let task_id: String = thread_rng().gen_ascii_chars().take(10).collect();
+
+    let request = types::accept_request(
+        "<framework-id>",
+        "<offer-id>",
+        "<agent-id>",
+        &task_id,
+        task_info,
+        &task_info.tty_mode,
+        upload::placeholder_uris(&task_info.upload),
+        task_info.persistent_volume.as_ref().map(|_| types::VolumePlacement::Create)
+    );
+
+    println!("{}", serde_json::to_string_pretty(&request).unwrap());
+}
+
+pub fn execute<'a>(mesos_host: &'a str, task_info: &'a types::RequestedTaskInfo) {
+    let events = EventLog::open(&task_info.events);
+    let leader_discovery = mesos::create_leader_discovery(mesos_host, &task_info.http, &task_info.discovery);
+    let mut master_uri: String = leader_discovery.discover();
+
+    let leader_updates = match task_info.discovery.follow_leader {
+        true => leader_discovery.watch(),
+        false => None
+    };
+
+    let body_content = subscribe_body(task_info, None);
 
     if task_info.verbose_output {
         println!("Subscribe message: {}", body_content);
@@ -554,51 +1374,198 @@ pub fn execute<'a>(mesos_host: &'a str, task_info: &'a types::RequestedTaskInfo)
 
     headers.set(ContentType::json());
 
-    let client: reqwest::Client = reqwest::ClientBuilder::new()
-        .default_headers(headers)
-        .build().unwrap();
+    let client: reqwest::Client = network::create_streaming_client(&task_info.http, headers);
 
-    match client.post(url)
-        .body(body_content)
-        .send() {
+    let (scheduler_uri, stream_id, response) = match subscribe(&client, &mut master_uri, body_content, task_info.verbose_output) {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            error!("{}", e);
+            result_exit(&events, 1);
+        }
+    };
 
-        Ok(mut response) => {
+    let uploads = match task_info.upload.files.is_empty() {
+        true => None,
+        false => Some(UploadServer::start(&task_info.upload, master_uri.as_str()))
+    };
 
-            match network::get_header_string_value("Mesos-Stream-Id", response.headers()) {
+    let mut scheduler = Scheduler::new(master_uri.clone(), scheduler_uri, task_info, stream_id, uploads, events);
+    scheduler.start();
 
-                Some(id) => {
+    let mut message_rx = read_events(response);
 
-                    if !id.is_empty() {
+    loop {
 
-                        if task_info.verbose_output {
-                            println!("Stream id {}", id);
-                        }
+        match message_rx.recv_timeout(Duration::from_millis(TICK_MILLIS)) {
+            Ok(message) => scheduler.handle_message(message),
+            Err(RecvTimeoutError::Timeout) => {},
+            Err(RecvTimeoutError::Disconnected) => {
 
-                        let mut scheduler = Scheduler::new(&scheduler_uri, task_info, id);
-                        scheduler.start();
+                match leader_updates {
+                    Some(ref leader_rx) if scheduler.is_subscribed() => {
+                        message_rx = follow_leader(&client, &mut scheduler, leader_rx, &mut master_uri);
+                    },
+                    _ => {
+                        error!("Lost connection to Mesos master");
+                        result_exit(&scheduler.events, 1);
+                    }
+                };
 
-                        loop {
-                            let message = network::read_next_message(&mut response);
-                            scheduler.handle_message(message);
-                        }
+            }
+        };
 
-                    } else {
-                        error!("Received empty steam id from Mesos");
-                        exit(1);
-                    }
+        scheduler.select_candidate();
 
-                },
-                None => {
-                    error!("Unable to get stream id from Mesos");
-                }
+        // A call was redirected and the scheduler subscribed to the new leader.
+        if let Some(event_stream) = scheduler.event_stream.take() {
+            master_uri = scheduler.master_url.clone();
+            message_rx = event_stream;
+        }
 
-            };
+    }
+
+}
+
+/**
+ * Subscribes with the volume's role and waits for an offer containing the persistent volume, then accepts it
+ * with DESTROY and UNRESERVE operations.
+ **/
+pub fn destroy_volume(mesos_host: &str, persistence_id: &str, role: &str, principal: Option<&str>, http: &types::HttpConfig, discovery: &types::DiscoveryConfig, timeout: Duration, verbose_output: bool) {
+    let leader_discovery = mesos::create_leader_discovery(mesos_host, http, discovery);
+    let mut master_uri: String = leader_discovery.discover();
+
+    match find_volume_agent(master_uri.as_str(), http, role, persistence_id) {
+        Ok(Some((_, hostname))) => {
+
+            if verbose_output {
+                println!("Persistent volume {} is on {}", persistence_id, hostname);
+            }
 
         },
+        Ok(None) => {
+            error!("No persistent volume {} is reserved for role {}", persistence_id, role);
+            exit(1);
+        },
         Err(e) => {
             error!("{}", e);
+            exit(1);
         }
+    };
 
+    let mut headers = Headers::new();
+
+    headers.set(ContentType::json());
+
+    let streaming_client: reqwest::Client = network::create_streaming_client(http, headers);
+    let body_content = framework_subscribe_body(None, Some(role), principal, None, None);
+
+    let (scheduler_uri, stream_id, response) = match subscribe(&streaming_client, &mut master_uri, body_content, verbose_output) {
+        Ok(subscription) => subscription,
+        Err(e) => {
+            error!("{}", e);
+            exit(1);
+        }
     };
 
+    let mut headers = Headers::new();
+
+    headers.set(ContentType::json());
+    headers.set(MesosStreamId(stream_id));
+
+    let client = network::create_client(http, headers);
+    let message_rx = read_events(response);
+    let started = Instant::now();
+    let mut framework_id = String::from("");
+    let mut exit_code = 0;
+
+    loop {
+
+        if started.elapsed() > timeout {
+            error!("Timeout waiting for persistent volume {} to be offered", persistence_id);
+            exit_code = 10;
+            break;
+        }
+
+        let message = match message_rx.recv_timeout(Duration::from_millis(TICK_MILLIS)) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => {
+                error!("Lost connection to Mesos master");
+                exit(1);
+            }
+        };
+
+        let value: Value = serde_json::from_str(message.as_str()).unwrap();
+
+        match value["type"].as_str() {
+            Some("SUBSCRIBED") => {
+                framework_id = String::from(value["subscribed"]["framework_id"]["value"].as_str().unwrap_or(""));
+            },
+            Some("OFFERS") => {
+                let offers: Vec<types::Offer> = match value["offers"]["offers"].as_array() {
+                    Some(offers) => offers.iter().map(types::Offer::from).collect(),
+                    None => vec![]
+                };
+
+                let mut declined: Vec<String> = vec![];
+                let mut destroyed: Option<String> = None;
+
+                for offer in offers {
+                    let volume = offer.persistent_volumes.iter()
+                        .find(|offered| offered.id == persistence_id)
+                        .cloned();
+
+                    match volume {
+                        Some(ref volume) if destroyed.is_none() => {
+                            let request = types::destroy_volume_request(framework_id.as_str(), offer.offer_id.as_str(), volume);
+
+                            let body_content = serde_json::to_string(&request).unwrap();
+
+                            if verbose_output {
+                                println!("Destroy: {}", body_content);
+                            }
+
+                            if let Err(e) = network::post(&client, scheduler_uri.as_str(), body_content) {
+                                error!("Unable to destroy persistent volume {}: {}", persistence_id, e);
+                                exit_code = 1;
+                            }
+
+                            destroyed = Some(offer.hostname.clone());
+                        },
+                        _ => declined.push(offer.offer_id.clone())
+                    };
+
+                }
+
+                if !declined.is_empty() {
+                    let request = types::decline_request(framework_id.as_str(), &declined[..], 5f32);
+
+                    if let Err(e) = network::post(&client, scheduler_uri.as_str(), serde_json::to_string(&request).unwrap()) {
+                        println!("Error sending decline to master: {}", e);
+                    }
+
+                }
+
+                if let Some(hostname) = destroyed {
+
+                    if exit_code == 0 {
+                        println!("Destroyed persistent volume {} on {}", persistence_id, hostname);
+                    }
+
+                    break;
+                }
+
+            },
+            _ => {}
+        };
+
+    }
+
+    let request = types::teardown_request(framework_id.as_str());
+
+    if let Err(e) = network::post(&client, scheduler_uri.as_str(), serde_json::to_string(&request).unwrap()) {
+        println!("Unable to send teardown call to master: {}. Exiting anyway.", e);
+    }
+
+    exit(exit_code);
 }
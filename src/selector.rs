@@ -0,0 +1,184 @@
+use constraints::ConstraintContext;
+use rand::{Rng, thread_rng};
+use std::cmp::Ordering;
+use types::{Offer, RequestedTaskInfo};
+
+/**
+ * Chooses which of the usable offers collected during the offer window the task should be launched on.
+ * Every candidate passed to a selector already satisfies the task's resource and constraint requirements.
+ **/
+pub trait OfferSelector {
+    fn select(&mut self, task_info: &RequestedTaskInfo, context: &ConstraintContext, candidates: &[Offer]) -> Option<usize>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    FirstFit,
+    BestFit,
+    WorstFit,
+    Random
+}
+
+pub struct FirstFitSelector;
+
+pub struct BestFitSelector;
+
+pub struct WorstFitSelector;
+
+pub struct RandomSelector;
+
+/**
+ * Narrows the candidates down to agents matching --prefer and not matching --avoid before handing them to
+ * another selector.  Falls back to the full candidate list if that would leave nothing to choose from.
+ **/
+pub struct AttributePreferenceSelector {
+    inner: Box<OfferSelector>
+}
+
+impl Strategy {
+
+    pub fn from(name: &str) -> Option<Strategy> {
+
+        match name {
+            "first-fit" => Some(Strategy::FirstFit),
+            "best-fit" => Some(Strategy::BestFit),
+            "worst-fit" | "spread" => Some(Strategy::WorstFit),
+            "random" => Some(Strategy::Random),
+            _ => None
+        }
+
+    }
+
+}
+
+fn leftover_ratio(offered: f32, required: f32) -> f32 {
+
+    if offered <= 0.0 {
+        0.0
+
+    } else {
+        (offered - required) / offered
+    }
+
+}
+
+/**
+ * How much of the offer would be left unused by the task.  0 is a perfect fit.
+ **/
+fn waste(task_info: &RequestedTaskInfo, offer: &Offer) -> f32 {
+    leftover_ratio(offer.cpus, task_info.cpus) +
+        leftover_ratio(offer.mem, task_info.mem) +
+        leftover_ratio(offer.disk, task_info.disk) +
+        leftover_ratio(offer.gpus as f32, task_info.gpus as f32)
+}
+
+fn compare_waste(task_info: &RequestedTaskInfo, a: &Offer, b: &Offer) -> Ordering {
+    waste(task_info, a).partial_cmp(&waste(task_info, b)).unwrap_or(Ordering::Equal)
+}
+
+impl OfferSelector for FirstFitSelector {
+
+    fn select(&mut self, _task_info: &RequestedTaskInfo, _context: &ConstraintContext, candidates: &[Offer]) -> Option<usize> {
+
+        match candidates.len() {
+            0 => None,
+            _ => Some(0)
+        }
+
+    }
+
+}
+
+impl OfferSelector for BestFitSelector {
+
+    fn select(&mut self, task_info: &RequestedTaskInfo, _context: &ConstraintContext, candidates: &[Offer]) -> Option<usize> {
+        (0..candidates.len()).min_by(|&a, &b| compare_waste(task_info, &candidates[a], &candidates[b]))
+    }
+
+}
+
+impl OfferSelector for WorstFitSelector {
+
+    fn select(&mut self, task_info: &RequestedTaskInfo, _context: &ConstraintContext, candidates: &[Offer]) -> Option<usize> {
+        (0..candidates.len()).max_by(|&a, &b| compare_waste(task_info, &candidates[a], &candidates[b]))
+    }
+
+}
+
+impl OfferSelector for RandomSelector {
+
+    fn select(&mut self, _task_info: &RequestedTaskInfo, _context: &ConstraintContext, candidates: &[Offer]) -> Option<usize> {
+
+        match candidates.len() {
+            0 => None,
+            count => Some(thread_rng().gen_range(0, count))
+        }
+
+    }
+
+}
+
+impl AttributePreferenceSelector {
+
+    pub fn new(inner: Box<OfferSelector>) -> AttributePreferenceSelector {
+        AttributePreferenceSelector { inner }
+    }
+
+    fn select_from(&mut self, task_info: &RequestedTaskInfo, context: &ConstraintContext, candidates: &[Offer], indexes: Vec<usize>) -> Option<usize> {
+        let subset: Vec<Offer> = indexes.iter().map(|&index| candidates[index].clone()).collect();
+
+        match self.inner.select(task_info, context, &subset[..]) {
+            Some(selected) => Some(indexes[selected]),
+            None => None
+        }
+
+    }
+
+}
+
+impl OfferSelector for AttributePreferenceSelector {
+
+    fn select(&mut self, task_info: &RequestedTaskInfo, context: &ConstraintContext, candidates: &[Offer]) -> Option<usize> {
+        let mut allowed: Vec<usize> = (0..candidates.len()).collect();
+
+        if !task_info.avoid.is_empty() {
+            // An agent is avoided only if it matches every --avoid option.
+            let not_avoided: Vec<usize> = allowed.iter()
+                .cloned()
+                .filter(|&index| task_info.avoid.check(&candidates[index].attributes, context).is_err())
+                .collect();
+
+            if !not_avoided.is_empty() {
+                allowed = not_avoided;
+            }
+
+        }
+
+        if !task_info.prefer.is_empty() {
+            let preferred: Vec<usize> = allowed.iter()
+                .cloned()
+                .filter(|&index| task_info.prefer.check(&candidates[index].attributes, context).is_ok())
+                .collect();
+
+            if !preferred.is_empty() {
+                allowed = preferred;
+            }
+
+        }
+
+        self.select_from(task_info, context, candidates, allowed)
+    }
+
+}
+
+pub fn create_selector(strategy: Strategy) -> Box<OfferSelector> {
+
+    let selector: Box<OfferSelector> = match strategy {
+        Strategy::FirstFit => Box::new(FirstFitSelector),
+        Strategy::BestFit => Box::new(BestFitSelector),
+        Strategy::WorstFit => Box::new(WorstFitSelector),
+        Strategy::Random => Box::new(RandomSelector)
+    };
+
+    Box::new(AttributePreferenceSelector::new(selector))
+}
@@ -1,13 +1,188 @@
+use constraints::Constraints;
+use selector::Strategy;
+use glob::Pattern;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
 use terminal_size::{Height, terminal_size, Width};
+use volumes::{VolumeSource, VolumeSpec};
 
 pub enum TTYMode {
     Interactive,
     Headless
 }
 
+#[derive(Clone, PartialEq)]
+pub enum MasterScheme {
+    Auto,
+    Http,
+    Https
+}
+
+#[derive(Clone)]
+pub struct DiscoveryConfig {
+    pub master_scheme: MasterScheme,
+    pub zk_timeout: Duration,
+    // Keep watching Zookeeper and re-subscribe to the new leader if the master fails over.
+    pub follow_leader: bool
+}
+
+#[derive(Clone)]
+pub struct TlsConfig {
+    pub ca_cert: Option<String>,
+    pub client_cert: Option<String>,
+    pub client_cert_password: String,
+    pub insecure_skip_hostname_verify: bool
+}
+
+#[derive(Clone)]
+pub struct HttpConfig {
+    pub tls: TlsConfig,
+    // Applies to each call made to masters and agents.  Event streams are never timed out.  reqwest 0.8 has one
+    // timeout covering connect, read and write, so these can't be set separately.
+    pub timeout: Option<Duration>
+}
+
+/**
+ * How headless consoles follow the task's output.  Auto attaches to the container's output stream where the
+ * agent supports it and otherwise reads the sandbox files.
+ **/
+#[derive(Clone, Debug, PartialEq)]
+pub enum FollowMode {
+    Poll,
+    Attach,
+    Auto
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum OutputFormat {
+    Text,
+    Json
+}
+
+pub struct EventConfig {
+    pub format: OutputFormat,
+    // Where JSON events go.  stderr when neither is given.
+    pub fd: Option<i32>,
+    pub file: Option<String>,
+    // Also emit the task's output as events.
+    pub include_output: bool
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum NetworkMode {
+    Host,
+    Bridge,
+    None,
+    // A Docker user defined network or a CNI network for the Mesos containerizer.
+    Named(String)
+}
+
+/**
+ * Options that weaken the container's isolation.  Only accepted with --allow-unsafe.
+ **/
+pub struct ContainerOptions {
+    pub docker_params: Vec<(String, String)>,
+    pub privileged: bool,
+    // Capability names as Mesos spells them, eg. NET_ADMIN.
+    pub capabilities: Vec<String>,
+    // (name, soft, hard) where name is lower case, eg. nofile.  None is unlimited.
+    pub rlimits: Vec<(String, Option<u64>, Option<u64>)>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum HealthCheckTarget {
+    // Scheme, port and path.
+    Http(String, u32, String),
+    Tcp(u32),
+    Command(String)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnhealthyAction {
+    // Only report the change.
+    Report,
+    // Kill the task and keep following it until it stops.
+    Kill,
+    // Stop following the task and exit straight away, which tears down the task.
+    Fail
+}
+
+pub struct HealthCheckConfig {
+    pub target: HealthCheckTarget,
+    pub interval: f64,
+    pub timeout: f64,
+    pub grace_period: f64,
+    pub consecutive_failures: u32,
+    pub on_unhealthy: UnhealthyAction
+}
+
+pub struct PersistentVolumeConfig {
+    pub id: String,
+    // MB of disk.
+    pub size: f32,
+    // Relative to the sandbox.
+    pub container_path: String
+}
+
+pub struct NetworkConfig {
+    // None uses the containerizer's default.  Bridge for Docker, the host's network for the command executor.
+    pub mode: Option<NetworkMode>,
+    pub labels: Vec<(String, String)>,
+    // (host port, container port, protocol)
+    pub port_mappings: Vec<(u64, u64, String)>
+}
+
+impl NetworkConfig {
+
+    pub fn host_ports(&self) -> Vec<u64> {
+        let mut ports: Vec<u64> = self.port_mappings.iter().map(|&(host_port, _, _)| host_port).collect();
+
+        ports.sort();
+        ports.dedup();
+        ports
+    }
+
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PrefixField {
+    Time,
+    Name,
+    Task,
+    Host,
+    Stream
+}
+
+/**
+ * Local files the task's output is copied to, on top of being streamed to the terminal.
+ **/
+pub struct OutputConfig {
+    pub stdout_file: Option<String>,
+    pub stderr_file: Option<String>,
+    // stdout and stderr combined, in the order they were read.
+    pub log_file: Option<String>,
+    // Files are rotated once they would grow past this many bytes.
+    pub max_size: Option<u64>,
+    // Number of rotated files kept alongside the current one.
+    pub max_files: u32,
+    // Fields each line of output is prefixed with.  Lines are passed through untouched when empty.
+    pub prefix: Vec<PrefixField>
+}
+
+/**
+ * Local files served to the Mesos fetcher, as (local path, path relative to the sandbox).
+ **/
+pub struct UploadConfig {
+    pub files: Vec<(String, String)>,
+    // Address agents use to reach this host.  Worked out from the route to the master when not given.
+    pub host: Option<String>,
+    pub port: u16
+}
+
 pub struct RequestedTaskInfo {
+    pub task_name: String,
     pub executor: String,
     pub image_name: Option<String>,
     pub cpus: f32,
@@ -19,12 +194,39 @@ pub struct RequestedTaskInfo {
     pub verbose_output: bool,
     pub tty: bool,
     pub tty_mode: TTYMode,
-    pub attrs: HashMap<String, String>,
-    pub volumes: Vec<(String, String, Option<String>)>,
+    pub constraints: Constraints,
+    pub volumes: Vec<VolumeSpec>,
     pub force_pull: bool,
     pub stderr: bool,
     pub shell: bool,
-    pub timeout: i64
+    pub timeout: i64,
+    pub explain: bool,
+    pub max_launch_attempts: u32,
+    pub offer_strategy: Strategy,
+    pub offer_window: u64,
+    pub prefer: Constraints,
+    pub avoid: Constraints,
+    pub http: HttpConfig,
+    pub discovery: DiscoveryConfig,
+    pub output: OutputConfig,
+    pub events: EventConfig,
+    pub follow_mode: FollowMode,
+    // Bytes requested per READ_FILE call when reading the sandbox.
+    pub chunk_size: u64,
+    // Sandbox files to download once the task stops, matched relative to the sandbox.
+    pub collect: Vec<Pattern>,
+    pub collect_dir: String,
+    pub upload: UploadConfig,
+    pub network: NetworkConfig,
+    pub container_options: ContainerOptions,
+    pub role: Option<String>,
+    pub principal: Option<String>,
+    pub persistent_volume: Option<PersistentVolumeConfig>,
+    pub health_check: Option<HealthCheckConfig>,
+    // Exit once the task is running and leave it to the attach, logs, wait and kill commands.
+    pub detach: bool,
+    // Print the ACCEPT call for the chosen offer instead of sending it.
+    pub dry_run: bool
 }
 
 #[derive(Serialize, Debug)]
@@ -82,11 +284,132 @@ pub enum VolumeMode {
     RO
 }
 
+#[derive(Serialize)]
+pub struct DockerImage {
+    name: String
+}
+
+#[derive(Serialize)]
+pub struct VolumeImage {
+    #[serde(rename = "type")]
+    image_type: String,
+    docker: DockerImage
+}
+
+#[derive(Serialize)]
+pub struct DriverOptions {
+    parameter: Vec<Parameter>
+}
+
+#[derive(Serialize)]
+pub struct DockerVolumeInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    driver: Option<String>,
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    driver_options: Option<DriverOptions>
+}
+
+#[derive(Serialize)]
+pub struct SandboxPathInfo {
+    #[serde(rename = "type")]
+    path_type: String,
+    path: String
+}
+
+#[derive(Serialize)]
+pub struct SecretReference {
+    name: String
+}
+
+#[derive(Serialize)]
+pub struct SecretInfo {
+    #[serde(rename = "type")]
+    secret_type: String,
+    reference: SecretReference
+}
+
+#[derive(Serialize)]
+pub struct VolumeSourceInfo {
+    #[serde(rename = "type")]
+    source_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    docker_volume: Option<DockerVolumeInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sandbox_path: Option<SandboxPathInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<SecretInfo>
+}
+
 #[derive(Serialize)]
 pub struct Volume {
     mode: VolumeMode,
     container_path: String,
-    host_path: String
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    image: Option<VolumeImage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<VolumeSourceInfo>
+}
+
+impl Volume {
+
+    fn from(spec: &VolumeSpec) -> Volume {
+        let mut volume = Volume {
+            mode: match spec.read_only {
+                true => VolumeMode::RO,
+                false => VolumeMode::RW
+            },
+            container_path: spec.container_path.clone(),
+            host_path: None,
+            image: None,
+            source: None
+        };
+
+        let source_info = |source_type: &str| VolumeSourceInfo {
+            source_type: String::from(source_type),
+            docker_volume: None,
+            sandbox_path: None,
+            secret: None
+        };
+
+        match spec.source {
+            // Relative host paths are relative to the sandbox for both containerizers.
+            VolumeSource::Host(ref path) | VolumeSource::Sandbox(ref path) => volume.host_path = Some(path.clone()),
+            VolumeSource::SandboxParent(ref path) => volume.source = Some(VolumeSourceInfo {
+                sandbox_path: Some(SandboxPathInfo { path_type: String::from("PARENT"), path: path.clone() }),
+                ..source_info("SANDBOX_PATH")
+            }),
+            VolumeSource::DockerVolume { ref name, ref driver, ref options } => volume.source = Some(VolumeSourceInfo {
+                docker_volume: Some(DockerVolumeInfo {
+                    driver: driver.clone(),
+                    name: name.clone(),
+                    driver_options: match options.is_empty() {
+                        true => None,
+                        false => Some(DriverOptions {
+                            parameter: options.iter().map(|&(ref key, ref value)| Parameter { key: key.clone(), value: value.clone() }).collect()
+                        })
+                    }
+                }),
+                ..source_info("DOCKER_VOLUME")
+            }),
+            VolumeSource::Secret(ref name) => volume.source = Some(VolumeSourceInfo {
+                secret: Some(SecretInfo {
+                    secret_type: String::from("REFERENCE"),
+                    reference: SecretReference { name: name.clone() }
+                }),
+                ..source_info("SECRET")
+            }),
+            VolumeSource::Image(ref name) => volume.image = Some(VolumeImage {
+                image_type: String::from("DOCKER"),
+                docker: DockerImage { name: name.clone() }
+            })
+        };
+
+        volume
+    }
+
 }
 
 #[derive(Serialize)]
@@ -95,8 +418,47 @@ pub struct Parameter {
     value: String
 }
 
+#[derive(Serialize)]
+pub struct CapabilityInfo {
+    capabilities: Vec<String>
+}
+
+#[derive(Serialize)]
+pub struct LinuxInfo {
+    effective_capabilities: CapabilityInfo,
+    bounding_capabilities: CapabilityInfo
+}
+
+#[derive(Serialize)]
+pub struct RLimit {
+    #[serde(rename = "type")]
+    limit_type: String,
+    // Both unset means unlimited.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    soft: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hard: Option<u64>
+}
+
+#[derive(Serialize)]
+pub struct RLimitInfo {
+    rlimits: Vec<RLimit>
+}
+
+#[derive(Serialize)]
+pub struct CommandUri {
+    pub value: String,
+    pub executable: bool,
+    pub extract: bool,
+    pub cache: bool,
+    // Relative to the sandbox.
+    pub output_file: String
+}
+
 #[derive(Serialize)]
 pub struct CommandInfo {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    uris: Vec<CommandUri>,
     value: String,
     arguments: Vec<String>,
     shell: bool,
@@ -110,6 +472,26 @@ pub struct PortMapping {
     protocol: String
 }
 
+#[derive(Serialize)]
+pub struct Label {
+    key: String,
+    value: String
+}
+
+#[derive(Serialize)]
+pub struct Labels {
+    labels: Vec<Label>
+}
+
+#[derive(Serialize)]
+pub struct NetworkInfo {
+    name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    labels: Option<Labels>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    port_mappings: Vec<PortMapping>
+}
+
 #[derive(Serialize)]
 pub struct DockerInfo {
     image: String,
@@ -137,9 +519,16 @@ pub struct TTYInfo {
 pub struct ContainerInfo {
     #[serde(rename = "type")]
     container_type: ContainerInfoType,
-    docker: DockerInfo,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    docker: Option<DockerInfo>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     volumes: Vec<Volume>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    network_infos: Vec<NetworkInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    linux_info: Option<LinuxInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rlimit_info: Option<RLimitInfo>,
     tty_info: Option<TTYInfo>
 }
 
@@ -148,7 +537,124 @@ pub struct Resource {
     name: String,
     #[serde(rename = "type")]
     resource_type: String,
-    scalar: Scalar
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scalar: Option<Scalar>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    ranges: Option<Ranges>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reservation: Option<ReservationInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disk: Option<DiskInfo>
+}
+
+#[derive(Serialize, Debug)]
+pub struct ReservationInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    principal: Option<String>
+}
+
+#[derive(Serialize, Debug)]
+pub struct Persistence {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    principal: Option<String>
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiskVolume {
+    mode: VolumeMode,
+    container_path: String
+}
+
+#[derive(Serialize, Debug)]
+pub struct DiskInfo {
+    persistence: Persistence,
+    volume: DiskVolume
+}
+
+impl Resource {
+
+    fn scalar(name: &str, value: f32) -> Resource {
+
+        Resource {
+            name: String::from(name),
+            resource_type: String::from("SCALAR"),
+            scalar: Some(Scalar { value }),
+            ranges: None,
+            role: None,
+            reservation: None,
+            disk: None
+        }
+
+    }
+
+    /**
+     * Disk reserved for role.  With a volume, the persistent volume created on that reservation.
+     **/
+    fn reserved_disk(size: f32, role: &str, principal: Option<&str>, volume: Option<(&str, &str)>) -> Resource {
+
+        Resource {
+            role: Some(String::from(role)),
+            reservation: Some(ReservationInfo { principal: principal.map(String::from) }),
+            disk: volume.map(|(id, container_path)| DiskInfo {
+                persistence: Persistence { id: String::from(id), principal: principal.map(String::from) },
+                volume: DiskVolume { mode: VolumeMode::RW, container_path: String::from(container_path) }
+            }),
+            ..Resource::scalar("disk", size)
+        }
+
+    }
+
+    /**
+     * A persistent volume exactly as it was offered, so the master matches it against the offer.
+     **/
+    fn offered_volume(volume: &OfferedVolume) -> Resource {
+
+        Resource {
+            disk: Some(DiskInfo {
+                persistence: Persistence { id: volume.id.clone(), principal: volume.persistence_principal.clone() },
+                volume: DiskVolume { mode: VolumeMode::RW, container_path: volume.container_path.clone() }
+            }),
+            ..Resource::reserved_disk(volume.size, volume.role.as_str(), volume.reservation_principal.as_ref().map(|principal| principal.as_str()), None)
+        }
+
+    }
+
+}
+
+/**
+ * A persistent volume found in an offer, with the reservation and disk info the master reported for it.
+ **/
+#[derive(Clone, Debug, PartialEq)]
+pub struct OfferedVolume {
+    pub id: String,
+    pub size: f32,
+    pub role: String,
+    pub reservation_principal: Option<String>,
+    pub persistence_principal: Option<String>,
+    pub container_path: String
+}
+
+/**
+ * Whether the task's persistent volume has to be created on the agent or is already there, as offered.
+ **/
+#[derive(Clone, Debug, PartialEq)]
+pub enum VolumePlacement {
+    Create,
+    Existing(OfferedVolume)
+}
+
+#[derive(Serialize, Debug)]
+pub struct Range {
+    begin: u64,
+    end: u64
+}
+
+#[derive(Serialize, Debug)]
+pub struct Ranges {
+    range: Vec<Range>
 }
 
 #[derive(Serialize)]
@@ -180,9 +686,179 @@ pub struct TaskInfo {
     container: Option<ContainerInfo>,
     command: CommandInfo,
     #[serde(skip_serializing_if = "Vec::is_empty")]
-    resources: Vec<Resource>
+    resources: Vec<Resource>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    health_check: Option<HealthCheck>
+}
+
+#[derive(Serialize)]
+pub enum HealthCheckType {
+    #[serde(rename = "HTTP")]
+    Http,
+    #[serde(rename = "TCP")]
+    Tcp,
+    #[serde(rename = "COMMAND")]
+    Command
+}
+
+#[derive(Serialize)]
+pub struct HttpCheckInfo {
+    scheme: String,
+    port: u32,
+    path: String
+}
+
+#[derive(Serialize)]
+pub struct TcpCheckInfo {
+    port: u32
+}
+
+#[derive(Serialize)]
+pub struct CheckCommand {
+    shell: bool,
+    value: String
+}
+
+#[derive(Serialize)]
+pub struct HealthCheck {
+    #[serde(rename = "type")]
+    check_type: HealthCheckType,
+    interval_seconds: f64,
+    timeout_seconds: f64,
+    grace_period_seconds: f64,
+    consecutive_failures: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    http: Option<HttpCheckInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tcp: Option<TcpCheckInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    command: Option<CheckCommand>
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AttributeValue {
+    Text(String),
+    Scalar(f64),
+    Ranges(Vec<(u64, u64)>),
+    Set(Vec<String>)
+}
+
+impl AttributeValue {
+
+    /**
+     * Parses a Mesos Attribute message.  Returns None for attribute types we don't understand.
+     **/
+    pub fn from(attr: &Value) -> Option<AttributeValue> {
+
+        match attr["type"].as_str() {
+            Some("SCALAR") => attr["scalar"]["value"].as_f64().map(AttributeValue::Scalar),
+            Some("RANGES") => {
+                let mut ranges: Vec<(u64, u64)> = vec![];
+
+                if let Some(range_values) = attr["ranges"]["range"].as_array() {
+
+                    for range in range_values {
+
+                        match (range["begin"].as_u64(), range["end"].as_u64()) {
+                            (Some(begin), Some(end)) => ranges.push((begin, end)),
+                            _ => {}
+                        }
+
+                    }
+
+                }
+
+                Some(AttributeValue::Ranges(ranges))
+            },
+            Some("SET") => {
+                let mut items: Vec<String> = vec![];
+
+                if let Some(item_values) = attr["set"]["item"].as_array() {
+
+                    for item in item_values {
+
+                        if let Some(item) = item.as_str() {
+                            items.push(String::from(item));
+                        }
+
+                    }
+
+                }
+
+                Some(AttributeValue::Set(items))
+            },
+            Some("TEXT") | None => Some(AttributeValue::Text(String::from(attr["text"]["value"].as_str().unwrap_or("")))),
+            Some(_) => None
+        }
+
+    }
+
 }
 
+impl fmt::Display for AttributeValue {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        match *self {
+            AttributeValue::Text(ref value) => write!(f, "{}", value),
+            AttributeValue::Scalar(value) => write!(f, "{}", value),
+            AttributeValue::Ranges(ref ranges) => {
+                let parts: Vec<String> = ranges.iter().map(|&(begin, end)| format!("{}-{}", begin, end)).collect();
+                write!(f, "[{}]", parts.join(","))
+            },
+            AttributeValue::Set(ref items) => write!(f, "{{{}}}", items.join(","))
+        }
+
+    }
+
+}
+
+/**
+ * Resources reserved for a role, either statically with the older role field or dynamically.
+ **/
+fn is_reserved(resource: &Value) -> bool {
+    resource["disk"].is_object()
+        || resource["role"].as_str().map_or(false, |role| role != "*")
+        || resource["reservations"].as_array().map_or(false, |reservations| !reservations.is_empty())
+        || resource["reservation"].is_object()
+}
+
+/**
+ * Converts a list of Mesos Attribute messages, as sent in offers and by the operator API, into a map.
+ **/
+pub fn parse_attributes(attrs: &Value, hostname: &str) -> HashMap<String, AttributeValue> {
+    let mut attributes: HashMap<String, AttributeValue> = HashMap::new();
+
+    if let Some(attrs) = attrs.as_array() {
+
+        for attr in attrs {
+
+            if let Some(name) = attr["name"].as_str() {
+
+                match AttributeValue::from(attr) {
+                    Some(value) => {
+                        attributes.insert(String::from(name), value);
+                    },
+                    None => {
+                        warn!("Ignoring attribute {} with unsupported type {}", name, attr["type"]);
+                    }
+                };
+
+            }
+
+        }
+
+    }
+
+    // If the hostname attribute isn't explicitly set as an attribute, we will implicitly add the agent's hostname.
+    if !attributes.contains_key("hostname") {
+        attributes.insert(String::from("hostname"), AttributeValue::Text(String::from(hostname)));
+    }
+
+    attributes
+}
+
+#[derive(Clone)]
 pub struct Offer {
     pub offer_id: String,
     pub agent_id: String,
@@ -193,7 +869,11 @@ pub struct Offer {
     pub gpus: i32,
     pub mem: f32,
     pub disk: f32,
-    pub attributes: HashMap<String, String>
+    pub ports: Vec<(u64, u64)>,
+    // Disk that isn't reserved or used by a volume, which a persistent volume can be created on.
+    pub unreserved_disk: f32,
+    pub persistent_volumes: Vec<OfferedVolume>,
+    pub attributes: HashMap<String, AttributeValue>
 }
 
 impl Offer {
@@ -203,6 +883,9 @@ impl Offer {
          let gpus: i32;
          let mem: f32;
          let disk: f32;
+         let mut ports: Vec<(u64, u64)> = vec![];
+         let mut unreserved_disk: f32 = 0.0;
+         let mut persistent_volumes: Vec<OfferedVolume> = vec![];
 
          match value["resources"].as_array() {
              Some(resources) => {
@@ -212,6 +895,52 @@ impl Offer {
 
                      match resource["name"].as_str() {
 
+                         Some("disk") if is_reserved(resource) => {
+                             let size = resource["scalar"]["value"].as_f64().unwrap_or(0.0) as f32;
+
+                             if let Some(id) = resource["disk"]["persistence"]["id"].as_str() {
+                                 // Reservation refinement puts the role and principal in the last reservation.
+                                 let reservation = match resource["reservations"].as_array().and_then(|reservations| reservations.last()) {
+                                     Some(reservation) => reservation,
+                                     None => &resource["reservation"]
+                                 };
+
+                                 persistent_volumes.push(OfferedVolume {
+                                     id: String::from(id),
+                                     size,
+                                     role: String::from(resource["role"].as_str().or(reservation["role"].as_str()).unwrap_or("*")),
+                                     reservation_principal: reservation["principal"].as_str().map(String::from),
+                                     persistence_principal: resource["disk"]["persistence"]["principal"].as_str().map(String::from),
+                                     container_path: String::from(resource["disk"]["volume"]["container_path"].as_str().unwrap_or(""))
+                                 });
+                             }
+
+                         },
+                         Some("disk") => {
+                             let size = resource["scalar"]["value"].as_f64().unwrap_or(0.0) as f32;
+
+                             unreserved_disk += size;
+                             resource_map.insert(String::from("disk"), unreserved_disk.to_string());
+                         },
+                         Some(_) if is_reserved(resource) => {
+                             // Reserved for a role.  The task only asks for unreserved cpus, mem, gpus and ports.
+                         },
+                         Some("ports") => {
+
+                             if let Some(range_values) = resource["ranges"]["range"].as_array() {
+
+                                 for range in range_values {
+
+                                     if let (Some(begin), Some(end)) = (range["begin"].as_u64(), range["end"].as_u64()) {
+                                         ports.push((begin, end));
+                                     }
+
+                                 }
+
+                             }
+
+                         },
+
                          Some(name) => {
                              resource_map.insert(String::from(name), match resource["scalar"]["value"].as_f64() {
                                  Some(value) => value.to_string(),
@@ -256,43 +985,7 @@ impl Offer {
              }
          };
 
-         let mut attributes: HashMap<String, String> = HashMap::new();
-
-         match value["attributes"].as_array() {
-
-             Some(attrs) => {
-
-                 for attr in attrs {
-
-                     match attr["name"].as_str() {
-
-                         Some(name) => {
-
-                             attributes.insert(String::from(name), match attr["text"]["value"].as_str() {
-                                 Some(value) => String::from(value),
-                                 None => String::from("")
-                             });
-
-                         },
-                         None => {
-                             // Not too sure yet what to do if we don't find a name.  Suspect response.
-                         }
-
-                     }
-
-                 }
-             },
-             None => {
-                 // Nothing to do.
-             }
-
-         };
-
-         // If the hostname attribute isn't explicitly set as an attribute, we will implicitly add
-         // the hostname in the offer
-         if !attributes.contains_key("hostname") {
-             attributes.insert(String::from("hostname"), String::from(value["hostname"].as_str().unwrap()));
-         }
+         let attributes = parse_attributes(&value["attributes"], value["hostname"].as_str().unwrap());
 
          Offer {
              offer_id: String::from(value["id"]["value"].as_str().unwrap()),
@@ -304,6 +997,9 @@ impl Offer {
              gpus,
              mem,
              disk,
+             ports,
+             unreserved_disk,
+             persistent_volumes,
              attributes
          }
 
@@ -322,6 +1018,16 @@ pub struct FrameworkInfo {
     pub user: String,
     pub name: String,
     pub capabilities: Vec<Capability>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<FrameworkID>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failover_timeout: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub checkpoint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub principal: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -333,6 +1039,8 @@ pub struct SubscribeType {
 pub struct SubscribeRequest {
     #[serde(rename = "type")]
     pub message_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub framework_id: Option<FrameworkID>,
     pub subscribe: SubscribeType,
 }
 
@@ -400,6 +1108,7 @@ pub struct Accept {
     offer_ids: Vec<ValueContainer>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     operations: Vec<Operation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     filters: Option<Filters>
 }
 
@@ -407,7 +1116,43 @@ pub struct Accept {
 pub struct Operation {
     #[serde(rename = "type")]
     operation_type: OperationType,
-    launch: Launch,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    launch: Option<Launch>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reserve: Option<ResourcesOperation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    unreserve: Option<ResourcesOperation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    create: Option<VolumesOperation>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    destroy: Option<VolumesOperation>
+}
+
+#[derive(Serialize)]
+pub struct ResourcesOperation {
+    resources: Vec<Resource>
+}
+
+#[derive(Serialize)]
+pub struct VolumesOperation {
+    volumes: Vec<Resource>
+}
+
+impl Operation {
+
+    fn new(operation_type: OperationType) -> Operation {
+
+        Operation {
+            operation_type,
+            launch: None,
+            reserve: None,
+            unreserve: None,
+            create: None,
+            destroy: None
+        }
+
+    }
+
 }
 
 #[derive(Serialize, Debug)]
@@ -456,6 +1201,34 @@ pub struct Call {
     accept: Accept,
 }
 
+#[derive(Serialize)]
+pub struct Decline {
+    offer_ids: Vec<ValueContainer>,
+    filters: Option<Filters>
+}
+
+#[derive(Serialize)]
+pub struct DeclineCall {
+    #[serde(rename = "type")]
+    message_type: CallType,
+    framework_id: FrameworkID,
+    decline: Decline
+}
+
+#[derive(Serialize)]
+pub struct SuppressCall {
+    #[serde(rename = "type")]
+    message_type: CallType,
+    framework_id: FrameworkID
+}
+
+#[derive(Serialize)]
+pub struct ReviveCall {
+    #[serde(rename = "type")]
+    message_type: CallType,
+    framework_id: FrameworkID
+}
+
 #[derive(Serialize)]
 pub struct TeardownCall {
     #[serde(rename = "type")]
@@ -470,6 +1243,20 @@ pub struct Acknowledge {
     uuid: String
 }
 
+#[derive(Serialize)]
+pub struct Kill {
+    task_id: ValueContainer,
+    agent_id: ValueContainer
+}
+
+#[derive(Serialize)]
+pub struct KillCall {
+    #[serde(rename = "type")]
+    message_type: CallType,
+    framework_id: FrameworkID,
+    kill: Kill
+}
+
 #[derive(Serialize)]
 pub struct AcknowledgeCall {
     #[serde(rename = "type")]
@@ -480,14 +1267,14 @@ pub struct AcknowledgeCall {
 
 #[derive(Serialize)]
 pub struct ReadFileRequestData {
-    pub length: u32,
-    pub offset: u32,
+    pub length: u64,
+    pub offset: u64,
     pub path: String
 }
 
 #[derive(Deserialize)]
 pub struct ReadFileResponseData {
-    pub size: u32,
+    pub size: u64,
     pub data: String
 }
 
@@ -505,6 +1292,40 @@ pub struct ReadFileResponse {
     pub read_file: ReadFileResponseData
 }
 
+#[derive(Serialize)]
+pub struct ListFilesRequestData {
+    pub path: String
+}
+
+#[derive(Serialize)]
+pub struct ListFilesRequest {
+    #[serde(rename = "type")]
+    pub request_type: String,
+    pub list_files: ListFilesRequestData
+}
+
+#[derive(Deserialize)]
+pub struct FileInfo {
+    pub path: String,
+    #[serde(default)]
+    pub size: u64,
+    #[serde(default)]
+    pub mode: u32
+}
+
+#[derive(Deserialize)]
+pub struct ListFilesResponseData {
+    #[serde(default)]
+    pub file_infos: Vec<FileInfo>
+}
+
+#[derive(Deserialize)]
+pub struct ListFilesResponse {
+    #[serde(rename = "type")]
+    pub request_type: String,
+    pub list_files: ListFilesResponseData
+}
+
 #[derive(Serialize)]
 pub struct AttachContainerOutput {
     pub container_id: ValueContainer
@@ -534,16 +1355,8 @@ pub struct AttachContainerOutputMessage {
 fn build_resources(task_info: &RequestedTaskInfo) -> Vec<Resource> {
     let mut resources = vec![
 
-        Resource {
-            name: String::from("cpus"),
-            resource_type: String::from("SCALAR"),
-            scalar: Scalar { value: task_info.cpus }
-        },
-        Resource {
-            name: String::from("mem"),
-            resource_type: String::from("SCALAR"),
-            scalar: Scalar { value: task_info.mem }
-        }
+        Resource::scalar("cpus", task_info.cpus),
+        Resource::scalar("mem", task_info.mem)
     ];
 
     match task_info.disk {
@@ -551,11 +1364,7 @@ fn build_resources(task_info: &RequestedTaskInfo) -> Vec<Resource> {
         _ => {
             resources.push(
 
-                Resource {
-                    name: String::from("disk"),
-                    resource_type: String::from("SCALAR"),
-                    scalar: Scalar { value: task_info.disk }
-                }
+                Resource::scalar("disk", task_info.disk)
 
             )
 
@@ -568,11 +1377,7 @@ fn build_resources(task_info: &RequestedTaskInfo) -> Vec<Resource> {
         _ => {
             resources.push(
 
-                Resource {
-                    name: String::from("gpus"),
-                    resource_type: String::from("SCALAR"),
-                    scalar: Scalar { value: task_info.gpus as f32 }
-                }
+                Resource::scalar("gpus", task_info.gpus as f32)
 
             )
 
@@ -580,9 +1385,265 @@ fn build_resources(task_info: &RequestedTaskInfo) -> Vec<Resource> {
 
     }
 
+    let host_ports = task_info.network.host_ports();
+
+    if !host_ports.is_empty() {
+
+        resources.push(
+            Resource {
+                name: String::from("ports"),
+                resource_type: String::from("RANGES"),
+                scalar: None,
+                ranges: Some(Ranges {
+                    range: host_ports.iter().map(|&port| Range { begin: port, end: port }).collect()
+                }),
+                role: None,
+                reservation: None,
+                disk: None
+            }
+        );
+
+    }
+
     resources
 }
 
+fn persistent_volume_resource(task_info: &RequestedTaskInfo, placement: Option<&VolumePlacement>, with_volume: bool) -> Option<Resource> {
+
+    match (task_info.persistent_volume.as_ref(), task_info.role.as_ref(), placement) {
+        (Some(_), _, Some(&VolumePlacement::Existing(ref offered))) => Some(Resource::offered_volume(offered)),
+        (Some(volume), Some(role), Some(&VolumePlacement::Create)) => {
+
+            Some(Resource::reserved_disk(
+                volume.size,
+                role.as_str(),
+                task_info.principal.as_ref().map(|principal| principal.as_str()),
+                match with_volume {
+                    true => Some((volume.id.as_str(), volume.container_path.as_str())),
+                    false => None
+                }
+            ))
+        },
+        _ => None
+    }
+
+}
+
+/**
+ * A new persistent volume is reserved and created in the same ACCEPT call that launches the task on it.
+ **/
+fn build_operations(task_info: &RequestedTaskInfo, placement: Option<&VolumePlacement>, launch: Launch) -> Vec<Operation> {
+    let mut operations: Vec<Operation> = vec![];
+
+    if placement == Some(&VolumePlacement::Create) {
+
+        if let Some(reservation) = persistent_volume_resource(task_info, placement, false) {
+            let mut reserve = Operation::new(OperationType::Reserve);
+            reserve.reserve = Some(ResourcesOperation { resources: vec![reservation] });
+            operations.push(reserve);
+        }
+
+        if let Some(volume) = persistent_volume_resource(task_info, placement, true) {
+            let mut create = Operation::new(OperationType::Create);
+            create.create = Some(VolumesOperation { volumes: vec![volume] });
+            operations.push(create);
+        }
+
+    }
+
+    let mut launch_operation = Operation::new(OperationType::Launch);
+    launch_operation.launch = Some(launch);
+    operations.push(launch_operation);
+
+    operations
+}
+
+/**
+ * Destroys a persistent volume and releases the disk reserved for it.
+ **/
+pub fn destroy_volume_request(framework_id: &str, offer_id: &str, volume: &OfferedVolume) -> Call {
+    let mut destroy = Operation::new(OperationType::Destroy);
+    let mut unreserve = Operation::new(OperationType::Unreserve);
+    let reservation_principal = volume.reservation_principal.as_ref().map(|principal| principal.as_str());
+
+    destroy.destroy = Some(VolumesOperation {
+        volumes: vec![Resource::offered_volume(volume)]
+    });
+
+    unreserve.unreserve = Some(ResourcesOperation {
+        resources: vec![Resource::reserved_disk(volume.size, volume.role.as_str(), reservation_principal, None)]
+    });
+
+    Call {
+        message_type: CallType::Accept,
+        framework_id: FrameworkID { value: String::from(framework_id) },
+        accept: Accept {
+            offer_ids: vec![ValueContainer { value: String::from(offer_id) }],
+            operations: vec![destroy, unreserve],
+            filters: None
+        }
+    }
+
+}
+
+fn build_health_check(task_info: &RequestedTaskInfo) -> Option<HealthCheck> {
+
+    task_info.health_check.as_ref().map(|config| {
+        let mut health_check = HealthCheck {
+            check_type: HealthCheckType::Command,
+            interval_seconds: config.interval,
+            timeout_seconds: config.timeout,
+            grace_period_seconds: config.grace_period,
+            consecutive_failures: config.consecutive_failures,
+            http: None,
+            tcp: None,
+            command: None
+        };
+
+        match config.target {
+            HealthCheckTarget::Http(ref scheme, port, ref path) => {
+                health_check.check_type = HealthCheckType::Http;
+                health_check.http = Some(HttpCheckInfo { scheme: scheme.clone(), port, path: path.clone() });
+            },
+            HealthCheckTarget::Tcp(port) => {
+                health_check.check_type = HealthCheckType::Tcp;
+                health_check.tcp = Some(TcpCheckInfo { port });
+            },
+            HealthCheckTarget::Command(ref command) => {
+                health_check.command = Some(CheckCommand { shell: true, value: command.clone() });
+            }
+        };
+
+        health_check
+    })
+
+}
+
+fn build_port_mappings(task_info: &RequestedTaskInfo) -> Vec<PortMapping> {
+
+    task_info.network.port_mappings.iter().map(|&(host_port, container_port, ref protocol)| PortMapping {
+        host_port: host_port as i32,
+        container_port: container_port as i32,
+        protocol: protocol.clone()
+    }).collect()
+
+}
+
+/**
+ * Named networks are attached through network_infos.  Docker user networks carry their port mappings in
+ * DockerInfo, while CNI networks on the Mesos containerizer carry them in the NetworkInfo.
+ **/
+fn build_network_infos(task_info: &RequestedTaskInfo, include_port_mappings: bool) -> Vec<NetworkInfo> {
+
+    match task_info.network.mode {
+        Some(NetworkMode::Named(ref name)) => vec![
+            NetworkInfo {
+                name: name.clone(),
+                labels: match task_info.network.labels.is_empty() {
+                    true => None,
+                    false => Some(Labels {
+                        labels: task_info.network.labels.iter().map(|&(ref key, ref value)| Label {
+                            key: key.clone(),
+                            value: value.clone()
+                        }).collect()
+                    })
+                },
+                port_mappings: match include_port_mappings {
+                    true => build_port_mappings(task_info),
+                    false => vec![]
+                }
+            }
+        ],
+        _ => vec![]
+    }
+
+}
+
+/**
+ * --cap-add and --rlimit are passed to Docker as the equivalent docker run options.
+ **/
+fn build_docker_parameters(task_info: &RequestedTaskInfo) -> Vec<Parameter> {
+    let options = &task_info.container_options;
+
+    let mut parameters: Vec<Parameter> = options.docker_params.iter().map(|&(ref key, ref value)| Parameter {
+        key: key.clone(),
+        value: value.clone()
+    }).collect();
+
+    for capability in &options.capabilities {
+        parameters.push(Parameter { key: String::from("cap-add"), value: capability.clone() });
+    }
+
+    for &(ref name, soft, hard) in &options.rlimits {
+        let limit = |value: Option<u64>| match value {
+            Some(value) => value.to_string(),
+            None => String::from("-1")
+        };
+
+        parameters.push(Parameter {
+            key: String::from("ulimit"),
+            value: format!("{}={}:{}", name, limit(soft), limit(hard))
+        });
+    }
+
+    parameters
+}
+
+fn needs_mesos_container(task_info: &RequestedTaskInfo) -> bool {
+
+    match task_info.network.mode {
+        Some(NetworkMode::Named(_)) => true,
+        _ => !task_info.container_options.capabilities.is_empty() ||
+            !task_info.container_options.rlimits.is_empty() ||
+            !task_info.volumes.is_empty()
+    }
+
+}
+
+/**
+ * The Mesos containerizer has no notion of adding to the default capabilities, so the listed capabilities
+ * become the task's full set.
+ **/
+fn build_linux_info(task_info: &RequestedTaskInfo) -> Option<LinuxInfo> {
+    let capabilities = &task_info.container_options.capabilities;
+
+    match capabilities.is_empty() {
+        true => None,
+        false => Some(LinuxInfo {
+            effective_capabilities: CapabilityInfo { capabilities: capabilities.clone() },
+            bounding_capabilities: CapabilityInfo { capabilities: capabilities.clone() }
+        })
+    }
+
+}
+
+fn build_rlimit_info(task_info: &RequestedTaskInfo) -> Option<RLimitInfo> {
+    let rlimits = &task_info.container_options.rlimits;
+
+    match rlimits.is_empty() {
+        true => None,
+        false => Some(RLimitInfo {
+            rlimits: rlimits.iter().map(|&(ref name, soft, hard)| RLimit {
+                limit_type: format!("RLMT_{}", name.to_uppercase()),
+                soft,
+                hard
+            }).collect()
+        })
+    }
+
+}
+
+fn docker_network(task_info: &RequestedTaskInfo) -> DockerInfoNetwork {
+
+    match task_info.network.mode {
+        Some(NetworkMode::Host) => DockerInfoNetwork::Host,
+        Some(NetworkMode::None) => DockerInfoNetwork::None,
+        Some(NetworkMode::Named(_)) => DockerInfoNetwork::User,
+        Some(NetworkMode::Bridge) | None => DockerInfoNetwork::Bridge
+    }
+
+}
+
 fn split(input: String) -> Vec<String> {
     let mut vec: Vec<String> = vec![];
 
@@ -646,7 +1707,7 @@ fn create_tty_info() -> TTYInfo {
 
 }
 
-pub fn accept_request<'a, 'b: 'a>(framework_id: &'a str, offer_id: &'a str, agent_id: &'a str, task_id: &'a str, task_info: &'b RequestedTaskInfo, tty_mode: &TTYMode) -> Call {
+pub fn accept_request<'a, 'b: 'a>(framework_id: &'a str, offer_id: &'a str, agent_id: &'a str, task_id: &'a str, task_info: &'b RequestedTaskInfo, tty_mode: &TTYMode, uris: Vec<CommandUri>, placement: Option<VolumePlacement>) -> Call {
     let env_args = task_info.env_args.clone();
 
     let mut env_vars: Vec<Variable> = vec![];
@@ -671,29 +1732,7 @@ pub fn accept_request<'a, 'b: 'a>(framework_id: &'a str, offer_id: &'a str, agen
         environment = None;
     }
 
-    let mut volumes: Vec<Volume> = vec![];
-    let volume_defs = task_info.volumes.clone();
-
-    if task_info.volumes.len() > 0 {
-
-        for (host_path, container_path, volume_mode) in volume_defs {
-
-            volumes.push( Volume {
-                mode: match volume_mode {
-                    None => VolumeMode::RW,
-                    Some(mode) => match mode.as_str() {
-                        "RW" => VolumeMode::RW,
-                        "RO" => VolumeMode::RO,
-                        _ => VolumeMode::RW
-                    }
-                },
-                container_path,
-                host_path,
-            });
-
-        }
-
-    }
+    let volumes: Vec<Volume> = task_info.volumes.iter().map(Volume::from).collect();
 
     Call {
         message_type: CallType::Accept,
@@ -702,30 +1741,34 @@ pub fn accept_request<'a, 'b: 'a>(framework_id: &'a str, offer_id: &'a str, agen
             offer_ids: vec![ValueContainer {
                 value: String::from(offer_id),
             }],
-            operations: vec![
-                Operation {
-                    operation_type: OperationType::Launch,
-                    launch: Launch {
+            operations: build_operations(task_info, placement.as_ref(),
+                    Launch {
                         task_infos: vec![
                             TaskInfo {
-                                name: String::from("rexe-command"),
+                                name: task_info.task_name.clone(),
                                 task_id: ValueContainer { value: String::from(task_id) },
                                 agent_id: ValueContainer { value: String::from(agent_id) },
                                 container: match task_info.executor.as_str() {
                                     "docker" => Some(ContainerInfo {
                                         container_type: ContainerInfoType::Docker,
                                         volumes,
-                                        docker: DockerInfo {
+                                        network_infos: build_network_infos(task_info, false),
+                                        linux_info: None,
+                                        rlimit_info: None,
+                                        docker: Some(DockerInfo {
                                             image: match task_info.image_name {
                                                 Some(ref image) => image.clone(),
                                                 None => String::from("")
                                             },
                                             force_pull_image: task_info.force_pull,
-                                            privileged: false,
-                                            network: DockerInfoNetwork::Bridge,
-                                            parameters: vec![],
-                                            port_mappings: vec![]
-                                        },
+                                            privileged: task_info.container_options.privileged,
+                                            network: docker_network(task_info),
+                                            parameters: build_docker_parameters(task_info),
+                                            port_mappings: match task_info.network.mode {
+                                                Some(NetworkMode::Host) | Some(NetworkMode::None) => vec![],
+                                                _ => build_port_mappings(task_info)
+                                            }
+                                        }),
                                         tty_info: match *tty_mode {
                                             TTYMode::Headless => {
 
@@ -747,9 +1790,22 @@ pub fn accept_request<'a, 'b: 'a>(framework_id: &'a str, offer_id: &'a str, agen
                                             }
                                         }
                                     }),
-                                    _ => None
+                                    // The command executor only needs a container to join CNI networks, mount volumes or set Linux options.
+                                    _ => match needs_mesos_container(task_info) {
+                                        true => Some(ContainerInfo {
+                                            container_type: ContainerInfoType::Mesos,
+                                            volumes,
+                                            network_infos: build_network_infos(task_info, true),
+                                            linux_info: build_linux_info(task_info),
+                                            rlimit_info: build_rlimit_info(task_info),
+                                            docker: None,
+                                            tty_info: None
+                                        }),
+                                        false => None
+                                    }
                                 },
                                 command: CommandInfo {
+                                    uris,
                                     value: match task_info.shell {
                                         true => {
                                             String::from(&*task_info.args)
@@ -766,13 +1822,19 @@ pub fn accept_request<'a, 'b: 'a>(framework_id: &'a str, offer_id: &'a str, agen
                                     environment
                                 },
                                 resources: {
-                                    build_resources(&task_info)
-                                }
+                                    let mut resources = build_resources(&task_info);
+
+                                    if let Some(volume) = persistent_volume_resource(task_info, placement.as_ref(), true) {
+                                        resources.push(volume);
+                                    }
+
+                                    resources
+                                },
+                                health_check: build_health_check(task_info)
                             }
                         ]
                     }
-                }
-            ],
+            ),
             filters: Some(Filters {
                 refuse_seconds: 600f32
             })
@@ -782,28 +1844,37 @@ pub fn accept_request<'a, 'b: 'a>(framework_id: &'a str, offer_id: &'a str, agen
 
 }
 
-pub fn decline_request<'a>(framework_id: &'a str, offer_id: &'a str, is_running: bool, refuse_seconds: f32) -> Call {
-    // Sending an accept message with no operations is the same as a decline.  Means less code.
+pub fn decline_request<'a>(framework_id: &'a str, offer_ids: &'a [String], refuse_seconds: f32) -> DeclineCall {
 
-    Call {
-        message_type: CallType::Accept,
+    DeclineCall {
+        message_type: CallType::Decline,
         framework_id: FrameworkID { value: String::from(framework_id) },
-        accept: Accept {
-            offer_ids: vec![ValueContainer {
-                value: String::from(offer_id),
-            }],
-            operations: vec![],
-            filters: match is_running {
-                true => Some(Filters {
-                    refuse_seconds
-                }),
-                false => None
-            }
+        decline: Decline {
+            offer_ids: offer_ids.iter().map(|offer_id| ValueContainer { value: offer_id.clone() }).collect(),
+            filters: Some(Filters {
+                refuse_seconds
+            })
         }
     }
 
 }
 
+pub fn suppress_request<'a>(framework_id: &'a str) -> SuppressCall {
+
+    SuppressCall {
+        message_type: CallType::Suppress,
+        framework_id: FrameworkID { value: String::from(framework_id) },
+    }
+}
+
+pub fn revive_request<'a>(framework_id: &'a str) -> ReviveCall {
+
+    ReviveCall {
+        message_type: CallType::Revive,
+        framework_id: FrameworkID { value: String::from(framework_id) },
+    }
+}
+
 pub fn teardown_request<'a>(framework_id: &'a str) -> TeardownCall {
 
     TeardownCall {
@@ -812,6 +1883,19 @@ pub fn teardown_request<'a>(framework_id: &'a str) -> TeardownCall {
     }
 }
 
+pub fn kill_request(framework_id: &str, agent_id: &str, task_id: &str) -> KillCall {
+
+    KillCall {
+        message_type: CallType::Kill,
+        framework_id: FrameworkID { value: String::from(framework_id) },
+        kill: Kill {
+            task_id: ValueContainer { value: String::from(task_id) },
+            agent_id: ValueContainer { value: String::from(agent_id) }
+        }
+    }
+
+}
+
 pub fn acknowledge_request(framework_id: &str, agent_id: &str, task_id: &str, uuid: &str) -> AcknowledgeCall {
 
     AcknowledgeCall {
@@ -0,0 +1,267 @@
+use rand::{Rng, thread_rng};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+use std::net::UdpSocket;
+use std::path::Path;
+use std::process::exit;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tiny_http::{Response, Server};
+use types;
+
+/**
+ * Serves the --upload files to the Mesos fetcher from a short lived HTTP server on this host.  Files are read
+ * into memory up front and served from a URL containing their SHA-256 digest and a random token, so the
+ * fetcher only ever gets the content that was hashed and nothing else on this host is exposed.
+ **/
+
+const POLL_MILLIS: u64 = 200;
+
+struct UploadedFile {
+    remote: String,
+    digest: String,
+    size: u64,
+    executable: bool,
+    data: Arc<Vec<u8>>
+}
+
+pub struct UploadServer {
+    base_url: String,
+    files: Vec<UploadedFile>,
+    // Digests of the files that have been sent in full.
+    fetched: Arc<Mutex<HashSet<String>>>,
+    running: Arc<Mutex<bool>>,
+    thread: Option<thread::JoinHandle<()>>
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[cfg(unix)]
+fn is_executable(file: &File) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    match file.metadata() {
+        Ok(metadata) => metadata.permissions().mode() & 0o111 != 0,
+        Err(_) => false
+    }
+
+}
+
+#[cfg(not(unix))]
+fn is_executable(_file: &File) -> bool {
+    false
+}
+
+fn read_file(local: &str, remote: &str) -> Result<UploadedFile, String> {
+
+    let mut file = match File::open(local) {
+        Ok(file) => file,
+        Err(e) => return Err(format!("Unable to open {}: {}", local, e))
+    };
+
+    let mut data: Vec<u8> = vec![];
+
+    if let Err(e) = file.read_to_end(&mut data) {
+        return Err(format!("Unable to read {}: {}", local, e));
+    }
+
+    let mut hasher = Sha256::default();
+    hasher.input(&data[..]);
+
+    Ok(UploadedFile {
+        remote: String::from(remote),
+        digest: to_hex(&hasher.result()[..]),
+        size: data.len() as u64,
+        executable: is_executable(&file),
+        data: Arc::new(data)
+    })
+}
+
+fn file_uri(base_url: &str, file: &UploadedFile) -> types::CommandUri {
+
+    types::CommandUri {
+        value: format!("{}/{}", base_url, file.digest),
+        executable: file.executable,
+        extract: false,
+        cache: false,
+        output_file: file.remote.clone()
+    }
+
+}
+
+/**
+ * The URIs the files would be fetched from, for --offline.  The address and token are only known once the
+ * server has started so placeholders are used.
+ **/
+pub fn placeholder_uris(config: &types::UploadConfig) -> Vec<types::CommandUri> {
+    let host = config.host.clone().unwrap_or(String::from("<upload-host>"));
+
+    let port = match config.port {
+        0 => String::from("<upload-port>"),
+        port => port.to_string()
+    };
+
+    let base_url = format!("http://{}:{}/<token>", host, port);
+
+    config.files.iter().map(|&(ref local, ref remote)| {
+
+        match read_file(local.as_str(), remote.as_str()) {
+            Ok(file) => file_uri(base_url.as_str(), &file),
+            Err(e) => {
+                error!("{}", e);
+                exit(1);
+            }
+        }
+
+    }).collect()
+}
+
+/**
+ * Works out which of our addresses the agents can reach us on by asking the OS how it would route to the
+ * master.  Nothing is actually sent.
+ **/
+fn advertised_host(master_url: &str) -> Option<String> {
+    let authority = match master_url.find("://") {
+        Some(index) => &master_url[index + 3..],
+        None => master_url
+    };
+
+    let authority = authority.split('/').next().unwrap_or(authority);
+
+    let socket = UdpSocket::bind("0.0.0.0:0").ok()?;
+    socket.connect(authority).ok()?;
+
+    socket.local_addr().ok().map(|address| address.ip().to_string())
+}
+
+impl UploadServer {
+
+    pub fn start(config: &types::UploadConfig, master_url: &str) -> UploadServer {
+        let mut files: Vec<UploadedFile> = vec![];
+
+        for &(ref local, ref remote) in &config.files {
+
+            match read_file(local.as_str(), remote.as_str()) {
+                Ok(file) => files.push(file),
+                Err(e) => {
+                    error!("{}", e);
+                    exit(1);
+                }
+            };
+
+        }
+
+        let server = match Server::http(("0.0.0.0", config.port)) {
+            Ok(server) => server,
+            Err(e) => {
+                error!("Unable to start upload server: {}", e);
+                exit(1);
+            }
+        };
+
+        let host = match config.host {
+            Some(ref host) => host.clone(),
+            None => match advertised_host(master_url) {
+                Some(host) => host,
+                None => {
+                    error!("Unable to work out the address agents can reach this host on.  Use --upload-host");
+                    exit(1);
+                }
+            }
+        };
+
+        let token: String = thread_rng().gen_ascii_chars().take(32).collect();
+        let base_url = format!("http://{}:{}/{}", host, server.server_addr().port(), token);
+
+        let running = Arc::new(Mutex::new(true));
+        let thread_running = Arc::clone(&running);
+        let fetched = Arc::new(Mutex::new(HashSet::new()));
+        let thread_fetched = Arc::clone(&fetched);
+        let prefix = format!("/{}/", token);
+        let served: Vec<(String, String, String, Arc<Vec<u8>>)> = files.iter()
+            .map(|file| (format!("{}{}", prefix, file.digest), file.remote.clone(), file.digest.clone(), Arc::clone(&file.data)))
+            .collect();
+
+        let thread = thread::spawn(move || {
+
+            while *thread_running.lock().unwrap() {
+
+                let request = match server.recv_timeout(Duration::from_millis(POLL_MILLIS)) {
+                    Ok(Some(request)) => request,
+                    Ok(None) => continue,
+                    Err(e) => {
+                        error!("Upload server stopped: {}", e);
+                        break;
+                    }
+                };
+
+                let file = served.iter().find(|&&(ref path, _, _, _)| path.as_str() == request.url());
+
+                match file {
+                    Some(&(_, ref remote, ref digest, ref data)) => {
+                        let remote_addr = *request.remote_addr();
+
+                        match request.respond(Response::from_data(data.as_ref().clone())) {
+                            Ok(_) => {
+                                info!("Sent {} ({} bytes, sha256 {}) to {}", remote, data.len(), digest, remote_addr);
+                                thread_fetched.lock().unwrap().insert(digest.clone());
+                            },
+                            Err(e) => warn!("Unable to send {} to {}: {}", remote, remote_addr, e)
+                        };
+
+                    },
+                    None => {
+
+                        if let Err(e) = request.respond(Response::from_string("Not found").with_status_code(404)) {
+                            warn!("Unable to send upload response: {}", e);
+                        }
+
+                    }
+                };
+
+            }
+
+        });
+
+        UploadServer {
+            base_url,
+            files,
+            fetched,
+            running,
+            thread: Some(thread)
+        }
+    }
+
+    pub fn uris(&self) -> Vec<types::CommandUri> {
+        self.files.iter().map(|file| file_uri(self.base_url.as_str(), file)).collect()
+    }
+
+    /**
+     * Checks every file was sent to the fetcher.  Files are served from the copy that was hashed so a file that
+     * was sent in full is the file that was uploaded.  The task may change its copy once it's running.
+     **/
+    pub fn verify(&self) -> Result<(), String> {
+        let fetched = self.fetched.lock().unwrap();
+
+        match self.files.iter().find(|file| !fetched.contains(&file.digest)) {
+            Some(file) => Err(format!("Uploaded file {} was never fetched from this host", file.remote)),
+            None => Ok(())
+        }
+
+    }
+
+    pub fn stop(&mut self) {
+        *self.running.lock().unwrap() = false;
+
+        if let Some(handle) = self.thread.take() {
+            let _ = handle.join();
+        }
+
+    }
+
+}
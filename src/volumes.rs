@@ -0,0 +1,247 @@
+/**
+ * Volumes requested with -v and --mount.
+ *
+ * -v keeps the original host_path:container_path[:RO|RW] form.  Host paths that aren't absolute are relative to
+ * the sandbox.  --mount takes comma separated key=value pairs, eg.
+ *
+ *   --mount type=volume,src=cache,dst=/cache,driver=rexray,opt=size=10
+ *   --mount type=secret,src=db-password,dst=secrets/db,ro
+ **/
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum VolumeSource {
+    // Absolute path on the agent.
+    Host(String),
+    // Path relative to the task's sandbox.
+    Sandbox(String),
+    // Path relative to the parent container's sandbox.
+    SandboxParent(String),
+    DockerVolume {
+        name: String,
+        driver: Option<String>,
+        options: Vec<(String, String)>
+    },
+    // Name of a secret held by the Mesos secret resolver.
+    Secret(String),
+    // Docker image whose root filesystem is mounted.
+    Image(String)
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct VolumeSpec {
+    pub source: VolumeSource,
+    pub container_path: String,
+    pub read_only: bool
+}
+
+fn is_relative(path: &str) -> bool {
+    !path.starts_with('/') && !path.split('/').any(|part| part == "..")
+}
+
+fn host_source(path: &str) -> VolumeSource {
+
+    match path.starts_with('/') {
+        true => VolumeSource::Host(String::from(path)),
+        false => VolumeSource::Sandbox(String::from(path))
+    }
+
+}
+
+/**
+ * Parses host_path:container_path[:RO|RW].  Host paths may contain ':' as the container path is taken to
+ * start at the last ":/".  Use --mount for container paths containing ':'.
+ **/
+pub fn parse_volume(def: &str) -> Result<VolumeSpec, String> {
+
+    let (paths, read_only) = match def.rfind(':') {
+        Some(index) => match def[index + 1..].to_uppercase().as_str() {
+            "RO" => (&def[..index], true),
+            "RW" => (&def[..index], false),
+            // With more than two parts the last one is a mode unless it is the start of an absolute container path.
+            mode if def[..index].contains(':') && !mode.starts_with('/') => {
+                return Err(format!("Invalid volume mode {} in {}.  Expected RO or RW", &def[index + 1..], def));
+            },
+            _ => (def, false)
+        },
+        None => return Err(format!("Invalid volume {}.  Expected host_path:container_path[:RO|RW]", def))
+    };
+
+    let split = match paths.rfind(":/") {
+        Some(index) => index,
+        None => match paths.rfind(':') {
+            Some(index) => index,
+            None => return Err(format!("Invalid volume {}.  Expected host_path:container_path[:RO|RW]", def))
+        }
+    };
+
+    let host_path = &paths[..split];
+    let container_path = &paths[split + 1..];
+
+    if host_path.is_empty() || container_path.is_empty() {
+        return Err(format!("Invalid volume {}.  Expected host_path:container_path[:RO|RW]", def));
+    }
+
+    Ok(VolumeSpec {
+        source: host_source(host_path),
+        container_path: String::from(container_path),
+        read_only
+    })
+}
+
+/**
+ * Parses the long form, eg. type=bind,src=/data,dst=/data,ro.  Types are bind, sandbox, parent, volume,
+ * secret and image.
+ **/
+pub fn parse_mount(def: &str) -> Result<VolumeSpec, String> {
+    let mut mount_type: Option<String> = None;
+    let mut source: Option<String> = None;
+    let mut target: Option<String> = None;
+    let mut driver: Option<String> = None;
+    let mut options: Vec<(String, String)> = vec![];
+    let mut read_only = false;
+
+    for field in def.split(',') {
+        let mut parts = field.splitn(2, '=');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts.next().map(|value| value.trim());
+
+        match (key, value) {
+            ("type", Some(value)) => mount_type = Some(value.to_lowercase()),
+            ("src", Some(value)) | ("source", Some(value)) => source = Some(String::from(value)),
+            ("dst", Some(value)) | ("destination", Some(value)) | ("target", Some(value)) => target = Some(String::from(value)),
+            ("driver", Some(value)) => driver = Some(String::from(value)),
+            ("opt", Some(value)) => {
+                let mut option = value.splitn(2, '=');
+
+                match (option.next(), option.next()) {
+                    (Some(name), Some(option_value)) if !name.is_empty() => options.push((String::from(name), String::from(option_value))),
+                    _ => return Err(format!("Invalid volume option {} in {}.  Expected opt=key=value", value, def))
+                };
+
+            },
+            ("ro", None) | ("readonly", None) => read_only = true,
+            ("ro", Some(value)) | ("readonly", Some(value)) => read_only = match value {
+                "true" | "1" => true,
+                "false" | "0" => false,
+                _ => return Err(format!("Invalid value {} for {} in {}", value, key, def))
+            },
+            ("", None) => {},
+            _ => return Err(format!("Unknown mount option {} in {}", field, def))
+        };
+
+    }
+
+    let source = match source {
+        Some(source) => source,
+        None => return Err(format!("Missing src in mount {}", def))
+    };
+
+    let container_path = match target {
+        Some(target) => target,
+        None => return Err(format!("Missing dst in mount {}", def))
+    };
+
+    let mount_type = mount_type.unwrap_or(String::from("bind"));
+
+    if mount_type != "volume" && (driver.is_some() || !options.is_empty()) {
+        return Err(format!("driver and opt are only supported by volume mounts in {}", def));
+    }
+
+    let source = match mount_type.as_str() {
+        "bind" => host_source(source.as_str()),
+        "sandbox" => VolumeSource::Sandbox(source),
+        "parent" => VolumeSource::SandboxParent(source),
+        "volume" => VolumeSource::DockerVolume { name: source, driver, options },
+        "secret" => VolumeSource::Secret(source),
+        "image" => VolumeSource::Image(source),
+        _ => return Err(format!("Unknown mount type {}.  Expected bind, sandbox, parent, volume, secret or image", mount_type))
+    };
+
+    Ok(VolumeSpec {
+        source,
+        container_path,
+        read_only
+    })
+}
+
+/**
+ * Checks the volume makes sense for the executor it will be used with.
+ **/
+pub fn validate(volume: &VolumeSpec, executor: &str) -> Result<(), String> {
+
+    if volume.container_path.is_empty() {
+        return Err(String::from("Volume container path can't be empty"));
+    }
+
+    if executor == "docker" && !volume.container_path.starts_with('/') {
+        return Err(format!("Container path {} must be absolute for the docker executor", volume.container_path));
+    }
+
+    match volume.source {
+        VolumeSource::Host(ref path) if !path.starts_with('/') => Err(format!("Host path {} must be absolute", path)),
+        VolumeSource::Sandbox(ref path) |
+        VolumeSource::SandboxParent(ref path) if path.is_empty() || !is_relative(path) => Err(format!("Sandbox path {} must be relative to the sandbox", path)),
+        VolumeSource::DockerVolume { ref name, .. } if name.is_empty() => Err(String::from("Docker volume name can't be empty")),
+        VolumeSource::Secret(ref name) if name.is_empty() => Err(String::from("Secret name can't be empty")),
+        VolumeSource::Image(ref name) if name.is_empty() => Err(String::from("Image name can't be empty")),
+        VolumeSource::SandboxParent(_) |
+        VolumeSource::Secret(_) |
+        VolumeSource::Image(_) if executor == "docker" => {
+            Err(format!("Volume for {} is only supported by the Mesos containerizer.  Use the exec executor", volume.container_path))
+        },
+        _ => Ok(())
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn volume(source: VolumeSource, container_path: &str, read_only: bool) -> VolumeSpec {
+        VolumeSpec { source, container_path: String::from(container_path), read_only }
+    }
+
+    #[test]
+    fn parses_host_and_container_paths() {
+        assert_eq!(parse_volume("/data:/data"), Ok(volume(VolumeSource::Host(String::from("/data")), "/data", false)));
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!(parse_volume("/data:/data:RO"), Ok(volume(VolumeSource::Host(String::from("/data")), "/data", true)));
+        assert_eq!(parse_volume("/data:/data:rw"), Ok(volume(VolumeSource::Host(String::from("/data")), "/data", false)));
+    }
+
+    #[test]
+    fn relative_host_paths_are_in_the_sandbox() {
+        assert_eq!(parse_volume("cache:/cache"), Ok(volume(VolumeSource::Sandbox(String::from("cache")), "/cache", false)));
+    }
+
+    #[test]
+    fn relative_container_paths() {
+        assert_eq!(parse_volume("/data:data:RO"), Ok(volume(VolumeSource::Host(String::from("/data")), "data", true)));
+        assert_eq!(parse_volume("/data:data"), Ok(volume(VolumeSource::Host(String::from("/data")), "data", false)));
+    }
+
+    #[test]
+    fn host_paths_can_contain_colons() {
+        assert_eq!(parse_volume("/mnt/a:b:/data"), Ok(volume(VolumeSource::Host(String::from("/mnt/a:b")), "/data", false)));
+        assert_eq!(parse_volume("/mnt/a:b:/data:RO"), Ok(volume(VolumeSource::Host(String::from("/mnt/a:b")), "/data", true)));
+    }
+
+    #[test]
+    fn rejects_unknown_modes() {
+        assert!(parse_volume("/a:/b:rx").is_err());
+        assert!(parse_volume("/a:b:rx").is_err());
+    }
+
+    #[test]
+    fn rejects_missing_paths() {
+        assert!(parse_volume("/data").is_err());
+        assert!(parse_volume(":/data").is_err());
+        assert!(parse_volume("/data:").is_err());
+        assert!(parse_volume("/data::RO").is_err());
+    }
+
+}
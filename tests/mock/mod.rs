@@ -0,0 +1,364 @@
+/**
+ * Fake Mesos master and agent for driving RExe end to end.  Both speak just enough HTTP/1.1 for reqwest,
+ * answer one request per connection and close it.
+ **/
+
+use base64;
+use serde_json;
+use serde_json::Value;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc;
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+pub const CONTAINER_ID: &'static str = "container-1";
+pub const WORK_DIR: &'static str = "/var/lib/mesos";
+
+struct Request {
+    method: String,
+    path: String,
+    body: String
+}
+
+fn read_request(stream: &TcpStream) -> Option<Request> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+
+    if reader.read_line(&mut request_line).ok()? == 0 {
+        return None;
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = String::from(parts.next()?);
+    let path = String::from(parts.next()?);
+    let mut content_length: usize = 0;
+
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).ok()?;
+
+        let header = header.trim();
+
+        if header.is_empty() {
+            break;
+        }
+
+        if let Some(index) = header.find(':') {
+
+            if header[..index].trim().eq_ignore_ascii_case("content-length") {
+                content_length = header[index + 1..].trim().parse().unwrap_or(0);
+            }
+
+        }
+
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body[..]).ok()?;
+
+    Some(Request {
+        method,
+        path,
+        body: String::from_utf8_lossy(&body[..]).into_owned()
+    })
+}
+
+fn respond(mut stream: TcpStream, status: &str, headers: &[(&str, &str)], body: &str) {
+    let mut response = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+
+    for &(name, value) in headers {
+        response.push_str(format!("{}: {}\r\n", name, value).as_str());
+    }
+
+    response.push_str("\r\n");
+    response.push_str(body);
+
+    let _ = stream.write_all(response.as_bytes());
+    let _ = stream.flush();
+}
+
+/**
+ * Starts a streaming response with no length, so the body runs until the connection is closed.
+ **/
+fn start_stream(stream: &mut TcpStream, headers: &[(&str, &str)]) {
+    let mut response = String::from("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\n");
+
+    for &(name, value) in headers {
+        response.push_str(format!("{}: {}\r\n", name, value).as_str());
+    }
+
+    response.push_str("\r\n");
+
+    let _ = stream.write_all(response.as_bytes());
+    let _ = stream.flush();
+}
+
+fn write_record(stream: &mut TcpStream, message: &Value) -> bool {
+    let message = message.to_string();
+
+    stream.write_all(format!("{}\n{}", message.len(), message).as_bytes()).and_then(|_| stream.flush()).is_ok()
+}
+
+fn serve<F>(handler: F) -> String where F: Fn(TcpStream, Request) + Send + Sync + 'static {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let handler = Arc::new(handler);
+
+    thread::spawn(move || {
+
+        for stream in listener.incoming() {
+
+            if let Ok(stream) = stream {
+                let handler = Arc::clone(&handler);
+
+                thread::spawn(move || {
+
+                    if let Some(request) = read_request(&stream) {
+                        handler(stream, request);
+                    }
+
+                });
+            }
+
+        }
+
+    });
+
+    url
+}
+
+/**
+ * How the master behaves once RExe has subscribed.
+ **/
+#[derive(Clone)]
+pub struct MasterScript {
+    // Offers sent straight after SUBSCRIBED.
+    pub offers: Vec<Value>,
+    // States reported for the task once it has been launched.
+    pub task_states: Vec<&'static str>,
+    // Close the event stream after SUBSCRIBED.
+    pub disconnect: bool
+}
+
+pub struct MockMaster {
+    pub url: String,
+    calls: Arc<Mutex<Vec<Value>>>
+}
+
+fn task_status(accept: &Value, state: &str, uuid: usize) -> Value {
+    let task_info = &accept["accept"]["operations"].as_array().unwrap().iter()
+        .find(|operation| operation["type"] == "LAUNCH")
+        .unwrap()["launch"]["task_infos"][0];
+
+    json!({
+        "type": "UPDATE",
+        "update": {
+            "status": {
+                "task_id": task_info["task_id"],
+                "agent_id": task_info["agent_id"],
+                "executor_id": { "value": task_info["task_id"]["value"] },
+                "container_status": {
+                    "container_id": { "value": CONTAINER_ID }
+                },
+                "state": state,
+                "source": "SOURCE_EXECUTOR",
+                "message": format!("Task is {}", state),
+                "uuid": format!("dXVpZC0{}", uuid)
+            }
+        }
+    })
+}
+
+impl MockMaster {
+
+    pub fn start(script: MasterScript) -> MockMaster {
+        let calls: Arc<Mutex<Vec<Value>>> = Arc::new(Mutex::new(vec![]));
+        let events: Arc<Mutex<Option<Sender<Option<Value>>>>> = Arc::new(Mutex::new(None));
+        let handler_calls = Arc::clone(&calls);
+
+        let url = serve(move |mut stream, request| {
+
+            if request.method != "POST" || request.path != "/api/v1/scheduler" {
+                respond(stream, "404 Not Found", &[], "");
+                return;
+            }
+
+            let call: Value = serde_json::from_str(request.body.as_str()).unwrap();
+            handler_calls.lock().unwrap().push(call.clone());
+
+            match call["type"].as_str() {
+                Some("SUBSCRIBE") => {
+                    let (events_tx, events_rx): (Sender<Option<Value>>, Receiver<Option<Value>>) = mpsc::channel();
+
+                    *events.lock().unwrap() = Some(events_tx.clone());
+
+                    start_stream(&mut stream, &[("Mesos-Stream-Id", "stream-1")]);
+
+                    events_tx.send(Some(json!({
+                        "type": "SUBSCRIBED",
+                        "subscribed": {
+                            "framework_id": { "value": "framework-1" },
+                            "heartbeat_interval_seconds": 15.0
+                        }
+                    }))).unwrap();
+
+                    if script.disconnect {
+                        events_tx.send(None).unwrap();
+                    } else if !script.offers.is_empty() {
+                        events_tx.send(Some(json!({
+                            "type": "OFFERS",
+                            "offers": { "offers": script.offers }
+                        }))).unwrap();
+                    }
+
+                    // Keep the stream open until told to close it.
+                    while let Ok(Some(event)) = events_rx.recv() {
+
+                        if !write_record(&mut stream, &event) {
+                            break;
+                        }
+
+                    }
+
+                },
+                Some("ACCEPT") => {
+                    respond(stream, "202 Accepted", &[], "");
+
+                    if let Some(ref events_tx) = *events.lock().unwrap() {
+
+                        for (index, state) in script.task_states.iter().enumerate() {
+                            let _ = events_tx.send(Some(task_status(&call, state, index)));
+                        }
+
+                    }
+
+                },
+                _ => respond(stream, "202 Accepted", &[], "")
+            };
+
+        });
+
+        MockMaster { url, calls }
+    }
+
+    /**
+     * Calls received so far of the given type, eg. ACCEPT.
+     **/
+    pub fn calls(&self, call_type: &str) -> Vec<Value> {
+        self.calls.lock().unwrap().iter().filter(|call| call["type"] == call_type).cloned().collect()
+    }
+
+}
+
+pub struct MockAgent {
+    port: u16
+}
+
+impl MockAgent {
+
+    /**
+     * Serves sandbox_stdout as what the task wrote before anyone attached and attached_stdout as what it writes
+     * once the container output is attached.  Like a real agent, attached output is also added to the sandbox.
+     **/
+    pub fn start(sandbox_stdout: &'static str, attached_stdout: &'static str) -> MockAgent {
+        let attached = Arc::new(Mutex::new(false));
+
+        let url = serve(move |mut stream, request| {
+
+            match (request.method.as_str(), request.path.as_str()) {
+                ("GET", "/state") => {
+                    respond(stream, "200 OK", &[("Content-Type", "application/json")], json!({ "flags": { "work_dir": WORK_DIR } }).to_string().as_str());
+                },
+                ("POST", "/api/v1") => {
+                    let call: Value = serde_json::from_str(request.body.as_str()).unwrap();
+
+                    match call["type"].as_str() {
+                        Some("READ_FILE") => {
+                            let path = call["read_file"]["path"].as_str().unwrap();
+                            let offset = call["read_file"]["offset"].as_u64().unwrap() as usize;
+                            let length = call["read_file"]["length"].as_u64().unwrap() as usize;
+
+                            let mut stdout = String::from(sandbox_stdout);
+
+                            if *attached.lock().unwrap() {
+                                stdout.push_str(attached_stdout);
+                            }
+
+                            let content: &[u8] = match path.ends_with("/stdout") {
+                                true => stdout.as_bytes(),
+                                false => &[]
+                            };
+
+                            let start = offset.min(content.len());
+                            let end = (offset + length).min(content.len());
+
+                            let body = json!({
+                                "type": "READ_FILE",
+                                "read_file": {
+                                    "size": content.len(),
+                                    "data": base64::encode(&content[start..end])
+                                }
+                            });
+
+                            respond(stream, "200 OK", &[("Content-Type", "application/json")], body.to_string().as_str());
+                        },
+                        Some("LIST_FILES") => {
+                            let body = json!({ "type": "LIST_FILES", "list_files": { "file_infos": [] } });
+                            respond(stream, "200 OK", &[("Content-Type", "application/json")], body.to_string().as_str());
+                        },
+                        Some("ATTACH_CONTAINER_OUTPUT") => {
+                            *attached.lock().unwrap() = true;
+
+                            start_stream(&mut stream, &[]);
+
+                            write_record(&mut stream, &json!({
+                                "type": "DATA",
+                                "data": {
+                                    "type": "STDOUT",
+                                    "data": base64::encode(attached_stdout.as_bytes())
+                                }
+                            }));
+
+                            // Closing the stream is how the agent reports the container has exited.
+                        },
+                        _ => respond(stream, "400 Bad Request", &[], "")
+                    };
+
+                },
+                _ => respond(stream, "404 Not Found", &[], "")
+            };
+
+        });
+
+        let port = url.rsplit(':').next().unwrap().parse::<u16>().unwrap();
+
+        MockAgent { port }
+    }
+
+    /**
+     * An offer from this agent.
+     **/
+    pub fn offer(&self, id: &str, cpus: f64, mem: f64) -> Value {
+
+        json!({
+            "id": { "value": id },
+            "framework_id": { "value": "framework-1" },
+            "agent_id": { "value": "agent-1" },
+            "hostname": "127.0.0.1",
+            "url": {
+                "scheme": "http",
+                "address": { "hostname": "127.0.0.1", "ip": "127.0.0.1", "port": self.port },
+                "path": "/slave(1)"
+            },
+            "resources": [
+                { "name": "cpus", "type": "SCALAR", "scalar": { "value": cpus } },
+                { "name": "mem", "type": "SCALAR", "scalar": { "value": mem } },
+                { "name": "disk", "type": "SCALAR", "scalar": { "value": 10000.0 } }
+            ],
+            "attributes": []
+        })
+    }
+
+}
@@ -0,0 +1,206 @@
+/**
+ * Runs the rexe binary against a fake master and agent on localhost.  scheduler::execute exits the process
+ * when the task ends, so each scenario runs rexe as a child process and checks its exit code, its output and
+ * the calls the master received.
+ **/
+
+extern crate base64;
+#[macro_use]
+extern crate serde_json;
+
+mod mock;
+
+use mock::{MasterScript, MockAgent, MockMaster};
+use std::env;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Longest any scenario should take.  rexe is killed after this so a hung scheduler fails the test.
+const RUN_TIMEOUT_SECONDS: u64 = 30;
+
+struct Run {
+    code: Option<i32>,
+    stdout: String,
+    stderr: String
+}
+
+/**
+ * Integration tests are built into target/<profile>/deps alongside the binary's directory.
+ **/
+fn rexe_path() -> PathBuf {
+    let mut path = env::current_exe().unwrap();
+
+    path.pop();
+
+    if path.ends_with("deps") {
+        path.pop();
+    }
+
+    path.push(format!("rexe{}", env::consts::EXE_SUFFIX));
+    path
+}
+
+fn read_all<R: Read + Send + 'static>(mut reader: R) -> thread::JoinHandle<String> {
+
+    thread::spawn(move || {
+        let mut output = String::new();
+        let _ = reader.read_to_string(&mut output);
+
+        output
+    })
+
+}
+
+fn rexe(master: &MockMaster, args: &[&str]) -> Run {
+    let mut child = Command::new(rexe_path())
+        .arg(master.url.as_str())
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let stdout = read_all(child.stdout.take().unwrap());
+    let stderr = read_all(child.stderr.take().unwrap());
+    let deadline = Instant::now() + Duration::from_secs(RUN_TIMEOUT_SECONDS);
+
+    let status = loop {
+
+        if let Some(status) = child.try_wait().unwrap() {
+            break status;
+        }
+
+        if Instant::now() > deadline {
+            let _ = child.kill();
+            panic!("rexe didn't exit within {} seconds", RUN_TIMEOUT_SECONDS);
+        }
+
+        thread::sleep(Duration::from_millis(50));
+    };
+
+    Run {
+        code: status.code(),
+        stdout: stdout.join().unwrap(),
+        stderr: stderr.join().unwrap()
+    }
+}
+
+fn run_echo(master: &MockMaster, follow_mode: &str, timeout: &str) -> Run {
+    rexe(master, &["exec", "-c", "1", "-m", "128", "-T", timeout, "--follow-mode", follow_mode, "--", "echo", "hello"])
+}
+
+#[test]
+fn finished_task_exits_zero_with_attached_output() {
+    let agent = MockAgent::start("before attach\n", "after attach\n");
+
+    let master = MockMaster::start(MasterScript {
+        offers: vec![agent.offer("offer-1", 4.0, 1024.0)],
+        task_states: vec!["TASK_STARTING", "TASK_RUNNING", "TASK_FINISHED"],
+        disconnect: false
+    });
+
+    let run = run_echo(&master, "attach", "10");
+
+    assert_eq!(run.code, Some(0), "stderr: {}", run.stderr);
+    assert!(run.stdout.contains("before attach\nafter attach\n"), "stdout: {}", run.stdout);
+    assert_eq!(run.stdout.matches("before attach").count(), 1, "stdout: {}", run.stdout);
+    assert_eq!(run.stdout.matches("after attach").count(), 1, "stdout: {}", run.stdout);
+
+    let accepts = master.calls("ACCEPT");
+    assert_eq!(accepts.len(), 1);
+
+    let accept = &accepts[0]["accept"];
+    assert_eq!(accept["offer_ids"][0]["value"], "offer-1");
+
+    let task_info = &accept["operations"][0]["launch"]["task_infos"][0];
+    assert_eq!(task_info["agent_id"]["value"], "agent-1");
+    assert_eq!(task_info["command"]["shell"], json!(true));
+    assert_eq!(task_info["command"]["value"].as_str().map(|value| value.trim()), Some("echo hello"));
+
+    assert_eq!(master.calls("TEARDOWN").len(), 1);
+}
+
+#[test]
+fn finished_task_exits_zero_with_polled_output() {
+    let agent = MockAgent::start("polled output\n", "");
+
+    let master = MockMaster::start(MasterScript {
+        offers: vec![agent.offer("offer-1", 4.0, 1024.0)],
+        task_states: vec!["TASK_RUNNING", "TASK_FINISHED"],
+        disconnect: false
+    });
+
+    let run = run_echo(&master, "poll", "10");
+
+    assert_eq!(run.code, Some(0), "stderr: {}", run.stderr);
+    assert!(run.stdout.contains("polled output"), "stdout: {}", run.stdout);
+}
+
+#[test]
+fn failed_task_exits_one_and_tears_down() {
+    let agent = MockAgent::start("", "");
+
+    let master = MockMaster::start(MasterScript {
+        offers: vec![agent.offer("offer-1", 4.0, 1024.0)],
+        task_states: vec!["TASK_RUNNING", "TASK_FAILED"],
+        disconnect: false
+    });
+
+    let run = run_echo(&master, "attach", "10");
+
+    assert_eq!(run.code, Some(1), "stderr: {}", run.stderr);
+    assert_eq!(master.calls("ACCEPT").len(), 1);
+    assert_eq!(master.calls("TEARDOWN").len(), 1);
+}
+
+#[test]
+fn no_offers_times_out() {
+    let master = MockMaster::start(MasterScript {
+        offers: vec![],
+        task_states: vec![],
+        disconnect: false
+    });
+
+    let run = run_echo(&master, "auto", "1");
+
+    assert_eq!(run.code, Some(10), "stderr: {}", run.stderr);
+    assert!(master.calls("ACCEPT").is_empty());
+}
+
+#[test]
+fn mismatched_offer_is_declined() {
+    let agent = MockAgent::start("", "");
+
+    let master = MockMaster::start(MasterScript {
+        offers: vec![agent.offer("offer-1", 0.5, 1024.0)],
+        task_states: vec![],
+        disconnect: false
+    });
+
+    let run = rexe(&master, &["exec", "-c", "2", "-m", "128", "-T", "2", "--", "echo", "hello"]);
+
+    assert_eq!(run.code, Some(10), "stderr: {}", run.stderr);
+    assert!(master.calls("ACCEPT").is_empty());
+
+    let declines = master.calls("DECLINE");
+    assert_eq!(declines.len(), 1);
+    assert_eq!(declines[0]["decline"]["offer_ids"][0]["value"], "offer-1");
+}
+
+#[test]
+fn master_disconnect_exits_one() {
+    let master = MockMaster::start(MasterScript {
+        offers: vec![],
+        task_states: vec![],
+        disconnect: true
+    });
+
+    let run = run_echo(&master, "auto", "10");
+
+    assert_eq!(run.code, Some(1), "stderr: {}", run.stderr);
+    assert!(master.calls("ACCEPT").is_empty());
+}